@@ -0,0 +1,108 @@
+use crate::CoincidenceError;
+use std::fs::{self, File};
+use std::path::Path;
+use tempfile::Builder;
+
+/// Writes a file atomically: the content is written to a temporary file in the same directory, synced to disk and
+/// renamed over `path`, and the directory is then synced so the rename survives a crash. If `write` fails the
+/// temporary file is removed and `path` is left untouched.
+///
+/// Readers of `path` therefore never see a partially written file, and `path` may be the very file `write` reads
+/// from: the original is only replaced once the new content is complete.
+///
+/// When `path` is a symbolic link the file it points to is replaced and the link is kept. When `path` already exists
+/// its permissions are carried over to the new file; otherwise the new file gets the permissions a regular file
+/// creation would give it.
+pub(crate) fn write_atomically<F>(path: &Path, write: F) -> Result<(), CoincidenceError>
+where
+    F: FnOnce(&mut File) -> Result<(), CoincidenceError>,
+{
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut builder = Builder::new();
+    builder.prefix(".csv_coincidence").suffix(".tmp");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+
+    let mut temp = builder.tempfile_in(dir)?;
+    if let Ok(metadata) = fs::metadata(&path) {
+        temp.as_file().set_permissions(metadata.permissions())?;
+    }
+
+    write(temp.as_file_mut())?;
+    temp.as_file().sync_all()?;
+    temp.persist(&path).map_err(|err| err.error)?;
+    sync_dir(dir)?;
+
+    Ok(())
+}
+
+/// Syncs a directory so that a rename inside it is durable. Directories cannot be opened as files on Windows, where
+/// the rename is made durable by the file system itself.
+fn sync_dir(dir: &Path) -> Result<(), CoincidenceError> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_failed_write_leaves_target_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        std::fs::write(&path, "original").unwrap();
+
+        let result = write_atomically(&path, |file| {
+            file.write_all(b"partial")?;
+            Err(CoincidenceError::EmptyPattern)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_existing_permissions_are_preserved() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        std::fs::write(&path, "original").unwrap();
+        std::fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomically(&path, |file| Ok(file.write_all(b"new")?)).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symbolic_links_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("data.csv");
+        let link = dir.path().join("link.csv");
+        std::fs::write(&target, "original").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomically(&link, |file| Ok(file.write_all(b"new")?)).unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+    }
+}
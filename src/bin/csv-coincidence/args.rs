@@ -0,0 +1,371 @@
+use csv_coincidence::{
+    CoincidenceError, ColumnSelector, ContextOptions, CountMode, MatchMode, MergeMode, MergeOptions, Pattern, Query,
+    ReplaceScope, Replacement, SearchOptions,
+};
+
+pub const USAGE: &str = "\
+Usage: csv-coincidence <COMMAND> [OPTIONS] <PATTERN> [FILE]
+       csv-coincidence select [OPTIONS] <QUERY> [FILE]
+
+Searches, counts or merges the fields of a CSV file matching a regular expression, or selects
+the records matching a query such as `name ~ /^J/ AND (city = \"Madrid\" OR age > 30)`.
+FILE defaults to `-`, which reads from stdin.
+
+Commands:
+  search    Print every matching field
+  count     Print the number of matches
+  merge     Print the records with their matching fields replaced
+  select    Print the whole records matching a query
+
+Options:
+  -c, --column <COL>          Only search this column: a header name, a zero-based index or a range
+                              such as `1..3` or `1..=3` (repeatable)
+  -x, --exclude <COL>         Never search this column (repeatable)
+  -w, --where <QUERY>         Only search, count or merge the records matching QUERY (repeatable)
+  -d, --delimiter <CHAR>      Field delimiter, a single byte or `\\t` [default: ,]
+      --no-headers            Treat the first row as data
+  -i, --ignore-case           Match case-insensitively
+  -F, --fixed-strings         Treat the pattern as literal text
+  -v, --invert-match          Make search and count report the selected fields that do not match
+  -m, --match-mode <MODE>     substring, full, start, end or word [default: substring]
+  -f, --format <FORMAT>       Output format of search and count: text, csv or json [default: text]
+  -o, --output <FILE>         Write the output to FILE instead of stdout (`-`); FILE is only replaced
+                              once the command succeeds, so it may be the input file
+      --records               Make search print whole matching records with their record numbers
+  -A, --after-context <N>     Make search print N records after each matching record (implies --records)
+  -B, --before-context <N>    Make search print N records before each matching record (implies --records)
+  -C, --context <N>           Make search print N records before and after each matching record
+                              (implies --records)
+      --count-mode <MODE>     What count counts: fields, records or occurrences [default: fields]
+  -r, --replacement <TEXT>    Text written by merge in place of the matches, `$1` and `${name}`
+                              expand capture groups [default: [MERGED]]
+      --matched-only          Make merge replace only the matched text instead of the whole field
+      --full-file             Make merge write the header row and the records without matches
+  -h, --help                  Print this help
+  -V, --version               Print the version
+
+Exit status: 0 if something matched, 1 if nothing matched, 2 if an error occurred.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Search,
+    Count,
+    Merge,
+    Select,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+/// What the command line asks for.
+#[derive(Debug)]
+pub enum Parsed {
+    Help,
+    Version,
+    Run(Box<Args>),
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub pattern: String,
+    pub query: Option<Query>,
+    pub filters: Vec<Query>,
+    pub input: String,
+    pub output: String,
+    pub format: Format,
+    pub records: bool,
+    pub before: usize,
+    pub after: usize,
+    pub delimiter: u8,
+    pub has_headers: bool,
+    pub columns: Vec<ColumnSelector>,
+    pub exclude: Vec<ColumnSelector>,
+    pub ignore_case: bool,
+    pub fixed_strings: bool,
+    pub invert: bool,
+    pub match_mode: MatchMode,
+    pub count_mode: CountMode,
+    pub replacement: String,
+    pub scope: ReplaceScope,
+    pub full_file: bool,
+}
+
+impl Args {
+    fn new(command: Command) -> Self {
+        Args {
+            command,
+            pattern: String::new(),
+            query: None,
+            filters: Vec::new(),
+            input: "-".to_string(),
+            output: "-".to_string(),
+            format: Format::Text,
+            records: false,
+            before: 0,
+            after: 0,
+            delimiter: b',',
+            has_headers: true,
+            columns: Vec::new(),
+            exclude: Vec::new(),
+            ignore_case: false,
+            fixed_strings: false,
+            invert: false,
+            match_mode: MatchMode::Substring,
+            count_mode: CountMode::Fields,
+            replacement: "[MERGED]".to_string(),
+            scope: ReplaceScope::Field,
+            full_file: false,
+        }
+    }
+
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .columns(self.columns.iter().cloned())
+            .exclude_columns(self.exclude.iter().cloned())
+            .match_mode(self.match_mode)
+            .count_mode(self.count_mode)
+            .invert(self.invert)
+            .filters(self.filters.iter().cloned())
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        let pattern = match self.fixed_strings {
+            true => Pattern::literal(self.pattern.as_str()),
+            false => Pattern::regex(self.pattern.as_str()),
+        };
+        pattern.case_insensitive(self.ignore_case)
+    }
+
+    pub fn context_options(&self) -> ContextOptions {
+        ContextOptions::new().before(self.before).after(self.after)
+    }
+
+    pub fn merge_options(&self) -> MergeOptions {
+        MergeOptions::new()
+            .replacement(Replacement::template(self.replacement.as_str()))
+            .scope(self.scope)
+            .mode(match self.full_file {
+                true => MergeMode::FullFile,
+                false => MergeMode::MatchedOnly,
+            })
+    }
+}
+
+/// Parses the command line arguments, without the program name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Parsed, String> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        Some("search") => Command::Search,
+        Some("count") => Command::Count,
+        Some("merge") => Command::Merge,
+        Some("select") => Command::Select,
+        Some("-h" | "--help" | "help") => return Ok(Parsed::Help),
+        Some("-V" | "--version") => return Ok(Parsed::Version),
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+        None => return Err("missing command".to_string()),
+    };
+
+    let mut parsed = Args::new(command);
+    let mut positionals = Vec::new();
+    let mut only_positionals = false;
+
+    while let Some(arg) = args.next() {
+        if only_positionals || arg == "-" || !arg.starts_with('-') {
+            positionals.push(arg);
+            continue;
+        }
+
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for `{}`", flag))
+        };
+
+        match flag.as_str() {
+            "--" => only_positionals = true,
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "-V" | "--version" => return Ok(Parsed::Version),
+            "-c" | "--column" => parsed.columns.push(parse_column(&value()?)?),
+            "-x" | "--exclude" => parsed.exclude.push(parse_column(&value()?)?),
+            "-w" | "--where" => parsed.filters.push(parse_query(&value()?)?),
+            "-d" | "--delimiter" => parsed.delimiter = parse_delimiter(&value()?)?,
+            "--no-headers" => parsed.has_headers = false,
+            "-i" | "--ignore-case" => parsed.ignore_case = true,
+            "-F" | "--fixed-strings" => parsed.fixed_strings = true,
+            "-v" | "--invert-match" => parsed.invert = true,
+            "-m" | "--match-mode" => parsed.match_mode = parse_match_mode(&value()?)?,
+            "-f" | "--format" => parsed.format = parse_format(&value()?)?,
+            "-o" | "--output" => parsed.output = value()?,
+            "--records" => parsed.records = true,
+            "-A" | "--after-context" => {
+                parsed.after = parse_number(&flag, &value()?)?;
+                parsed.records = true;
+            }
+            "-B" | "--before-context" => {
+                parsed.before = parse_number(&flag, &value()?)?;
+                parsed.records = true;
+            }
+            "-C" | "--context" => {
+                parsed.after = parse_number(&flag, &value()?)?;
+                parsed.before = parsed.after;
+                parsed.records = true;
+            }
+            "--count-mode" => parsed.count_mode = parse_count_mode(&value()?)?,
+            "-r" | "--replacement" => parsed.replacement = value()?,
+            "--matched-only" => parsed.scope = ReplaceScope::Matched,
+            "--full-file" => parsed.full_file = true,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+
+    if parsed.invert && command == Command::Merge {
+        return Err("`--invert-match` cannot be used with merge".to_string());
+    }
+
+    let mut positionals = positionals.into_iter();
+    parsed.pattern = match command {
+        Command::Select => positionals.next().ok_or("missing query")?,
+        _ => positionals.next().ok_or("missing pattern")?,
+    };
+    if command == Command::Select {
+        parsed.query = Some(parse_query(&parsed.pattern)?);
+        parsed.records = true;
+    }
+    if let Some(input) = positionals.next() {
+        parsed.input = input;
+    }
+    if let Some(extra) = positionals.next() {
+        return Err(format!("unexpected argument `{}`", extra));
+    }
+
+    Ok(Parsed::Run(Box::new(parsed)))
+}
+
+fn parse_column(value: &str) -> Result<ColumnSelector, String> {
+    let index = |text: &str| text.parse::<usize>().map_err(|_| format!("invalid column range `{}`", value));
+
+    if let Some((start, end)) = value.split_once("..=") {
+        Ok(ColumnSelector::from(index(start)?..=index(end)?))
+    } else if let Some((start, end)) = value.split_once("..") {
+        Ok(ColumnSelector::from(index(start)?..index(end)?))
+    } else if let Ok(index) = value.parse::<usize>() {
+        Ok(ColumnSelector::Index(index))
+    } else {
+        Ok(ColumnSelector::Name(value.to_string()))
+    }
+}
+
+/// Parses a query, pointing at the offending character when it is not valid.
+fn parse_query(value: &str) -> Result<Query, String> {
+    Query::parse(value).map_err(|err| match err {
+        CoincidenceError::Query { position, .. } => format!("{}\n\n  {}\n  {}^", err, value, " ".repeat(position)),
+        err => err.to_string(),
+    })
+}
+
+fn parse_number(flag: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{}` expects a number of records, got `{}`", flag, value))
+}
+
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        [byte] => Ok(*byte),
+        b"\\t" => Ok(b'\t'),
+        _ => Err(format!("the delimiter must be a single byte, got `{}`", value)),
+    }
+}
+
+fn parse_match_mode(value: &str) -> Result<MatchMode, String> {
+    match value {
+        "substring" => Ok(MatchMode::Substring),
+        "full" => Ok(MatchMode::FullField),
+        "start" => Ok(MatchMode::StartsWith),
+        "end" => Ok(MatchMode::EndsWith),
+        "word" => Ok(MatchMode::WordBoundary),
+        _ => Err(format!("unknown match mode `{}`", value)),
+    }
+}
+
+fn parse_format(value: &str) -> Result<Format, String> {
+    match value {
+        "text" => Ok(Format::Text),
+        "csv" => Ok(Format::Csv),
+        "json" => Ok(Format::Json),
+        _ => Err(format!("unknown format `{}`", value)),
+    }
+}
+
+fn parse_count_mode(value: &str) -> Result<CountMode, String> {
+    match value {
+        "fields" => Ok(CountMode::Fields),
+        "records" => Ok(CountMode::Records),
+        "occurrences" => Ok(CountMode::Occurrences),
+        _ => Err(format!("unknown count mode `{}`", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Result<Args, String> {
+        match parse(args.iter().map(|arg| arg.to_string()))? {
+            Parsed::Run(args) => Ok(*args),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_flags_and_positionals() {
+        let args = run(&["count", "-d", ";", "--column=name", "-c", "1..=2", "-i", "^j", "data.csv"]).unwrap();
+
+        assert_eq!(args.command, Command::Count);
+        assert_eq!((args.pattern.as_str(), args.input.as_str()), ("^j", "data.csv"));
+        assert_eq!(args.delimiter, b';');
+        assert!(args.ignore_case);
+        assert!(matches!(&args.columns[..], [ColumnSelector::Name(name), ColumnSelector::Range(range)]
+            if name == "name" && *range == (1..3)));
+    }
+
+    #[test]
+    fn test_defaults_to_stdin_and_allows_dash_patterns() {
+        let args = run(&["search", "--", "-x-"]).unwrap();
+
+        assert_eq!((args.pattern.as_str(), args.input.as_str(), args.output.as_str()), ("-x-", "-", "-"));
+    }
+
+    #[test]
+    fn test_context_flags_imply_records() {
+        let args = run(&["search", "-C", "2", "--after-context=1", "x"]).unwrap();
+        assert!(args.records);
+        assert_eq!(args.context_options(), ContextOptions::new().before(2).after(1));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(run(&["search"]).unwrap_err().contains("missing pattern"));
+        assert!(run(&["search", "-d"]).unwrap_err().contains("missing value"));
+        assert!(run(&["search", "--delimiter", "ab", "x"]).unwrap_err().contains("single byte"));
+        assert!(run(&["search", "-C", "two", "x"]).unwrap_err().contains("number of records"));
+        assert!(run(&["select"]).unwrap_err().contains("missing query"));
+        assert!(run(&["select", "age >> 3"]).unwrap_err().ends_with("\n  age >> 3\n       ^"));
+        assert!(run(&["merge", "-v", "x"]).unwrap_err().contains("cannot be used with merge"));
+        assert!(run(&["search", "--bogus", "x"]).unwrap_err().contains("unknown option"));
+        assert!(parse(["find".to_string()]).unwrap_err().contains("unknown command"));
+    }
+}
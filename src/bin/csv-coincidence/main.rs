@@ -0,0 +1,358 @@
+//! The `csv-coincidence` command-line tool: searches, counts and merges the fields of a CSV file matching a pattern,
+//! and selects the records matching a query.
+
+mod args;
+// The library keeps these crate-private, so the binary compiles its own copy of the same source.
+#[path = "../../atomic.rs"]
+mod atomic;
+#[path = "../../json.rs"]
+mod json;
+
+use args::{Args, Command, Format, Parsed, USAGE};
+use atomic::write_atomically;
+use csv_coincidence::{
+    count_report_in, find_records_in, matches_in, merge_coincidence_in, query_records_in, CoincidenceError,
+    ContextRecord, Match,
+};
+use json::json_string;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let code = match args::parse(env::args().skip(1)) {
+        Ok(Parsed::Help) => {
+            print!("{}", USAGE);
+            0
+        }
+        Ok(Parsed::Version) => {
+            println!("csv-coincidence {}", env!("CARGO_PKG_VERSION"));
+            0
+        }
+        Ok(Parsed::Run(args)) => match run(&args) {
+            Ok(true) => 0,
+            Ok(false) => 1,
+            Err(CoincidenceError::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => 0,
+            Err(err) => {
+                eprintln!("csv-coincidence: {}", err);
+                2
+            }
+        },
+        Err(message) => {
+            eprintln!("csv-coincidence: {}\n\n{}", message, USAGE);
+            2
+        }
+    };
+
+    process::exit(code);
+}
+
+/// Runs the command, returning whether anything matched. An output file is only replaced once the command has
+/// succeeded, so it may be the input file itself.
+fn run(args: &Args) -> Result<bool, CoincidenceError> {
+    let input: Box<dyn Read> = match args.input.as_str() {
+        "-" => Box::new(io::stdin().lock()),
+        path => Box::new(File::open(path)?),
+    };
+
+    match args.output.as_str() {
+        "-" => execute(args, input, BufWriter::new(io::stdout().lock())),
+        path => {
+            let mut matched = false;
+            write_atomically(Path::new(path), |file| {
+                matched = execute(args, input, BufWriter::new(file))?;
+                Ok(())
+            })?;
+            Ok(matched)
+        }
+    }
+}
+
+/// Runs the command from `input` to `output`, returning whether anything matched.
+fn execute<R: Read, W: Write>(args: &Args, input: R, mut output: W) -> Result<bool, CoincidenceError> {
+    let matched = match args.command {
+        Command::Search if args.records => records(args, input, &mut output)?,
+        Command::Search => search(args, input, &mut output)?,
+        Command::Select => records(args, input, &mut output)?,
+        Command::Count => count(args, input, &mut output)?,
+        Command::Merge => {
+            let options = args.search_options();
+            merge_coincidence_in(input, &mut output, args.pattern(), &options, &args.merge_options())? > 0
+        }
+    };
+
+    output.flush()?;
+    Ok(matched)
+}
+
+fn search<R: Read, W: Write>(args: &Args, input: R, output: W) -> Result<bool, CoincidenceError> {
+    let found = matches_in(input, args.pattern(), &args.search_options())?;
+    let mut matched = false;
+
+    match args.format {
+        Format::Text => {
+            let mut output = output;
+            for result in found {
+                writeln!(output, "{}", result?.value)?;
+                matched = true;
+            }
+        }
+        Format::Csv => {
+            let mut wtr = csv::Writer::from_writer(output);
+            wtr.write_record(["record", "line", "column", "header", "value", "start", "end"])?;
+            for result in found {
+                let m = result?;
+                wtr.write_record([
+                    m.record.to_string(),
+                    m.line.to_string(),
+                    m.column.to_string(),
+                    m.header.unwrap_or_default(),
+                    m.value,
+                    m.start.to_string(),
+                    m.end.to_string(),
+                ])?;
+                matched = true;
+            }
+            wtr.flush()?;
+        }
+        Format::Json => {
+            let mut output = output;
+            write!(output, "[")?;
+            for result in found {
+                write!(output, "{}{}", if matched { "," } else { "" }, match_json(&result?))?;
+                matched = true;
+            }
+            writeln!(output, "]")?;
+        }
+    }
+
+    Ok(matched)
+}
+
+/// Prints the whole matching records and their context: grep-style in text, with a `record` and a `matched` column
+/// in CSV.
+fn records<R: Read, W: Write>(args: &Args, input: R, mut output: W) -> Result<bool, CoincidenceError> {
+    let found = match &args.query {
+        Some(query) => query_records_in(input, query, &args.search_options(), &args.context_options())?,
+        None => find_records_in(input, args.pattern(), &args.search_options(), &args.context_options())?,
+    };
+    let matched = found.records.iter().any(|r| r.matched);
+
+    match args.format {
+        Format::Text => {
+            if let Some(headers) = &found.headers {
+                writeln!(output, "{}", csv_line(headers, args.delimiter)?)?;
+            }
+            let mut previous = None;
+            for r in &found.records {
+                if previous.is_some_and(|previous| previous + 1 != r.record) {
+                    writeln!(output, "--")?;
+                }
+                let separator = if r.matched { ':' } else { '-' };
+                writeln!(output, "{}{}{}", r.record, separator, csv_line(&r.fields, args.delimiter)?)?;
+                previous = Some(r.record);
+            }
+        }
+        Format::Csv => {
+            let mut wtr = csv::WriterBuilder::new()
+                .delimiter(args.delimiter)
+                .flexible(true)
+                .from_writer(output);
+            if let Some(headers) = &found.headers {
+                wtr.write_record(["record", "matched"].into_iter().chain(headers.iter().map(String::as_str)))?;
+            }
+            for r in &found.records {
+                let numbers = [r.record.to_string(), r.matched.to_string()];
+                wtr.write_record(numbers.iter().chain(&r.fields))?;
+            }
+            wtr.flush()?;
+        }
+        Format::Json => {
+            let headers = found.headers.as_deref().map_or("null".to_string(), json_array);
+            let records: Vec<String> = found.records.iter().map(record_json).collect();
+            writeln!(output, "{{\"headers\":{},\"records\":[{}]}}", headers, records.join(","))?;
+        }
+    }
+
+    Ok(matched)
+}
+
+fn count<R: Read, W: Write>(args: &Args, input: R, mut output: W) -> Result<bool, CoincidenceError> {
+    let report = count_report_in(input, args.pattern(), &args.search_options())?;
+    let totals = [
+        ("fields", report.fields),
+        ("records", report.records),
+        ("occurrences", report.occurrences),
+        ("records_scanned", report.records_scanned),
+        ("fields_scanned", report.fields_scanned),
+    ];
+
+    match args.format {
+        Format::Text => writeln!(output, "{}", report.get(args.count_mode))?,
+        Format::Csv => {
+            let names: Vec<&str> = totals.iter().map(|(name, _)| *name).collect();
+            let values: Vec<String> = totals.iter().map(|(_, value)| value.to_string()).collect();
+            writeln!(output, "{}\n{}", names.join(","), values.join(","))?;
+        }
+        Format::Json => {
+            let fields: Vec<String> = totals.iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+            writeln!(output, "{{{}}}", fields.join(","))?;
+        }
+    }
+
+    Ok(report.get(args.count_mode) > 0)
+}
+
+fn match_json(m: &Match) -> String {
+    format!(
+        "{{\"record\":{},\"line\":{},\"column\":{},\"header\":{},\"value\":{},\"start\":{},\"end\":{}}}",
+        m.record,
+        m.line,
+        m.column,
+        m.header.as_deref().map_or("null".to_string(), json_string),
+        json_string(&m.value),
+        m.start,
+        m.end
+    )
+}
+
+fn record_json(r: &ContextRecord) -> String {
+    format!(
+        "{{\"record\":{},\"line\":{},\"matched\":{},\"fields\":{}}}",
+        r.record,
+        r.line,
+        r.matched,
+        json_array(&r.fields)
+    )
+}
+
+fn json_array(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|value| json_string(value)).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Formats the fields as a single CSV line, without the record terminator.
+fn csv_line(fields: &[String], delimiter: u8) -> Result<String, CoincidenceError> {
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    wtr.write_record(fields)?;
+    let mut line = wtr.into_inner().map_err(|err| err.into_error())?;
+    line.pop();
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &[&str]) -> Args {
+        match args::parse(command.iter().map(|arg| arg.to_string())).unwrap() {
+            Parsed::Run(args) => *args,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    fn output<F>(f: F) -> (bool, String)
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<bool, CoincidenceError>,
+    {
+        let mut output = Vec::new();
+        let matched = f(&mut output).unwrap();
+        (matched, String::from_utf8(output).unwrap())
+    }
+
+    const DATA: &str = "name,city\nJhon,madrid\nMarta,\"Paris \"\"FR\"\"\"\n";
+
+    #[test]
+    fn test_search_formats() {
+        let text = output(|out| search(&args(&["search", "-c", "name", "^J"]), DATA.as_bytes(), out));
+        assert_eq!(text, (true, "Jhon\n".to_string()));
+
+        let csv = output(|out| search(&args(&["search", "-f", "csv", "FR"]), DATA.as_bytes(), out));
+        assert_eq!(
+            csv.1,
+            "record,line,column,header,value,start,end\n2,3,1,city,\"Paris \"\"FR\"\"\",7,9\n"
+        );
+
+        let json = output(|out| search(&args(&["search", "-f", "json", "FR"]), DATA.as_bytes(), out));
+        assert_eq!(
+            json.1,
+            concat!(
+                r#"[{"record":2,"line":3,"column":1,"header":"city","#,
+                r#""value":"Paris \"FR\"","start":7,"end":9}]"#,
+                "\n"
+            )
+        );
+
+        let invalid = output(|out| search(&args(&["search", "-v", "-c", "city", "^[a-z]+$"]), DATA.as_bytes(), out));
+        assert_eq!(invalid, (true, "Paris \"FR\"\n".to_string()));
+
+        let none = output(|out| search(&args(&["search", "-f", "json", "zzz"]), DATA.as_bytes(), out));
+        assert_eq!(none, (false, "[]\n".to_string()));
+    }
+
+    #[test]
+    fn test_records_with_context() {
+        let data = "id,level\n1,info\n2,error\n3,info\n4,info\n5,info\n6,error\n";
+
+        let text = output(|out| records(&args(&["search", "-B", "1", "-c", "level", "error"]), data.as_bytes(), out));
+        assert_eq!(text, (true, "id,level\n1-1,info\n2:2,error\n--\n5-5,info\n6:6,error\n".to_string()));
+
+        let csv = output(|out| records(&args(&["search", "--records", "-f", "csv", "^2$"]), data.as_bytes(), out));
+        assert_eq!(csv.1, "record,matched,id,level\n2,true,2,error\n");
+
+        let json = output(|out| records(&args(&["search", "-A", "1", "-f", "json", "^3$"]), data.as_bytes(), out));
+        assert_eq!(
+            json.1,
+            concat!(
+                r#"{"headers":["id","level"],"records":[{"record":3,"line":4,"matched":true,"fields":["3","info"]},"#,
+                r#"{"record":4,"line":5,"matched":false,"fields":["4","info"]}]}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_select_and_where() {
+        let data = "name,city,age\nJhon,Madrid,25\nJane,Paris,41\nMarta,Madrid,52\n";
+
+        let select = output(|out| records(&args(&["select", "city = 'Madrid' AND age > 30"]), data.as_bytes(), out));
+        assert_eq!(select, (true, "name,city,age\n3:Marta,Madrid,52\n".to_string()));
+
+        let count = output(|out| count(&args(&["count", "-w", "age < 50", "-c", "city", "^M"]), data.as_bytes(), out));
+        assert_eq!(count, (true, "1\n".to_string()));
+    }
+
+    #[test]
+    fn test_count_modes_and_formats() {
+        let text = output(|out| count(&args(&["count", "--count-mode", "occurrences", "a"]), DATA.as_bytes(), out));
+        assert_eq!(text, (true, "4\n".to_string()));
+
+        let json = output(|out| count(&args(&["count", "-f", "json", "-i", "^m"]), DATA.as_bytes(), out));
+        assert_eq!(
+            json.1,
+            "{\"fields\":2,\"records\":2,\"occurrences\":2,\"records_scanned\":2,\"fields_scanned\":4}\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_output_may_be_the_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("people.csv");
+        std::fs::write(&path, DATA).unwrap();
+        let path = path.to_str().unwrap();
+        let expected = "name,city\n[MERGED],madrid\nMarta,\"Paris \"\"FR\"\"\"\n";
+
+        let merged = run(&args(&["merge", "--full-file", "-c", "name", "^J", path, "-o", path])).unwrap();
+        assert!(merged);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), expected);
+
+        let unmatched = run(&args(&["merge", "--full-file", "zzz", path, "-o", path])).unwrap();
+        assert!(!unmatched);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), expected);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::CoincidenceError;
+use csv::StringRecord;
+use regex::Regex;
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+
+/// Selects one or more columns of a CSV file.
+///
+/// Selectors are resolved once against the header row. Name and header-pattern selectors require a header row;
+/// index and range selectors also work on headerless input.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_partial_matches_in, ColumnSelector, SearchOptions};
+///
+/// let data = "name,city\nJhon,Madrid\n";
+/// let options = SearchOptions::new().column("name");
+///
+/// assert_eq!(find_partial_matches_in(data.as_bytes(), r"^[A-Z]", &options).unwrap(), vec!["Jhon"]);
+///
+/// let options = SearchOptions::new().column(ColumnSelector::header_regex("^c").unwrap());
+/// assert_eq!(find_partial_matches_in(data.as_bytes(), r"^[A-Z]", &options).unwrap(), vec!["Madrid"]);
+/// ```
+#[derive(Clone)]
+pub enum ColumnSelector {
+    /// The column whose header is exactly this name.
+    Name(String),
+    /// The column at this zero-based index.
+    Index(usize),
+    /// The columns within this half-open range of zero-based indices.
+    Range(Range<usize>),
+    /// Every column whose header matches this regular expression.
+    HeaderRegex(Regex),
+}
+
+impl ColumnSelector {
+    /// Creates a selector matching every column whose header matches the given regular expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if the pattern is invalid.
+    pub fn header_regex(pattern: &str) -> Result<Self, CoincidenceError> {
+        Ok(ColumnSelector::HeaderRegex(crate::compile_pattern(pattern)?))
+    }
+
+    fn resolve_into(&self, headers: Option<&StringRecord>, ranges: &mut Vec<Range<usize>>) -> Result<(), CoincidenceError> {
+        match (self, headers) {
+            (ColumnSelector::Name(name), Some(headers)) => {
+                let index = headers
+                    .iter()
+                    .position(|header| header == name)
+                    .ok_or_else(|| CoincidenceError::UnknownColumn(name.clone()))?;
+                ranges.push(index..index + 1);
+            }
+            (ColumnSelector::HeaderRegex(re), Some(headers)) => {
+                let before = ranges.len();
+                ranges.extend(
+                    headers
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, header)| re.is_match(header))
+                        .map(|(index, _)| index..index + 1),
+                );
+
+                if ranges.len() == before {
+                    return Err(CoincidenceError::UnknownColumn(re.as_str().to_string()));
+                }
+            }
+            (ColumnSelector::Name(name), None) => return Err(CoincidenceError::UnknownColumn(name.clone())),
+            (ColumnSelector::HeaderRegex(re), None) => {
+                return Err(CoincidenceError::UnknownColumn(re.as_str().to_string()))
+            }
+            (ColumnSelector::Index(index), headers) => {
+                check_bounds(*index, headers)?;
+                ranges.push(*index..*index + 1);
+            }
+            (ColumnSelector::Range(range), headers) => {
+                if !range.is_empty() {
+                    check_bounds(range.end - 1, headers)?;
+                }
+                ranges.push(range.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn check_bounds(index: usize, headers: Option<&StringRecord>) -> Result<(), CoincidenceError> {
+    match headers {
+        Some(headers) if index >= headers.len() => Err(CoincidenceError::ColumnOutOfRange {
+            index,
+            len: headers.len(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+impl fmt::Debug for ColumnSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnSelector::Name(name) => f.debug_tuple("Name").field(name).finish(),
+            ColumnSelector::Index(index) => f.debug_tuple("Index").field(index).finish(),
+            ColumnSelector::Range(range) => f.debug_tuple("Range").field(range).finish(),
+            ColumnSelector::HeaderRegex(re) => f.debug_tuple("HeaderRegex").field(&re.as_str()).finish(),
+        }
+    }
+}
+
+impl From<&str> for ColumnSelector {
+    fn from(name: &str) -> Self {
+        ColumnSelector::Name(name.to_string())
+    }
+}
+
+impl From<String> for ColumnSelector {
+    fn from(name: String) -> Self {
+        ColumnSelector::Name(name)
+    }
+}
+
+impl From<usize> for ColumnSelector {
+    fn from(index: usize) -> Self {
+        ColumnSelector::Index(index)
+    }
+}
+
+impl From<Range<usize>> for ColumnSelector {
+    fn from(range: Range<usize>) -> Self {
+        ColumnSelector::Range(range)
+    }
+}
+
+impl From<RangeInclusive<usize>> for ColumnSelector {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        ColumnSelector::Range(*range.start()..*range.end() + 1)
+    }
+}
+
+/// The set of columns a search applies to, resolved from the selectors in `SearchOptions`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ColumnFilter {
+    included: Option<Vec<Range<usize>>>,
+    excluded: Vec<Range<usize>>,
+}
+
+impl ColumnFilter {
+    pub(crate) fn resolve(
+        columns: &[ColumnSelector],
+        exclude_columns: &[ColumnSelector],
+        headers: Option<&StringRecord>,
+    ) -> Result<Self, CoincidenceError> {
+        let included = if columns.is_empty() {
+            None
+        } else {
+            Some(resolve_all(columns, headers)?)
+        };
+
+        Ok(ColumnFilter {
+            included,
+            excluded: resolve_all(exclude_columns, headers)?,
+        })
+    }
+
+    pub(crate) fn is_selected(&self, column: usize) -> bool {
+        let included = match &self.included {
+            Some(ranges) => ranges.iter().any(|range| range.contains(&column)),
+            None => true,
+        };
+
+        included && !self.excluded.iter().any(|range| range.contains(&column))
+    }
+}
+
+/// Resolves the selectors to individual column indices, in selector order.
+pub(crate) fn resolve_columns(
+    selectors: &[ColumnSelector],
+    headers: Option<&StringRecord>,
+) -> Result<Vec<usize>, CoincidenceError> {
+    Ok(resolve_all(selectors, headers)?.into_iter().flatten().collect())
+}
+
+fn resolve_all(selectors: &[ColumnSelector], headers: Option<&StringRecord>) -> Result<Vec<Range<usize>>, CoincidenceError> {
+    let mut ranges = Vec::new();
+    for selector in selectors {
+        selector.resolve_into(headers, &mut ranges)?;
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> StringRecord {
+        StringRecord::from(vec!["name", "email", "city", "zip"])
+    }
+
+    #[test]
+    fn test_resolve_names_ranges_and_exclusions() {
+        let columns = vec![ColumnSelector::from("name"), ColumnSelector::from(2..=3)];
+        let exclude = vec![ColumnSelector::from("zip")];
+        let filter = ColumnFilter::resolve(&columns, &exclude, Some(&headers())).unwrap();
+
+        let selected: Vec<usize> = (0..4).filter(|&c| filter.is_selected(c)).collect();
+        assert_eq!(selected, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_resolve_header_regex() {
+        let columns = vec![ColumnSelector::header_regex("^(name|city)$").unwrap()];
+        let filter = ColumnFilter::resolve(&columns, &[], Some(&headers())).unwrap();
+
+        let selected: Vec<usize> = (0..4).filter(|&c| filter.is_selected(c)).collect();
+        assert_eq!(selected, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_resolve_reports_missing_columns() {
+        let result = ColumnFilter::resolve(&["phone".into()], &[], Some(&headers()));
+        assert!(matches!(result, Err(CoincidenceError::UnknownColumn(name)) if name == "phone"));
+
+        let result = ColumnFilter::resolve(&[7.into()], &[], Some(&headers()));
+        assert!(matches!(result, Err(CoincidenceError::ColumnOutOfRange { index: 7, len: 4 })));
+
+        let result = ColumnFilter::resolve(&["name".into()], &[], None);
+        assert!(matches!(result, Err(CoincidenceError::UnknownColumn(_))));
+    }
+
+    #[test]
+    fn test_indices_without_headers_are_unbounded() {
+        let filter = ColumnFilter::resolve(&[7.into()], &[], None).unwrap();
+        assert!(filter.is_selected(7));
+        assert!(!filter.is_selected(0));
+    }
+}
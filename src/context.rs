@@ -0,0 +1,266 @@
+use crate::pattern::Matcher;
+use crate::{validate_csv_extension, CoincidenceError, Pattern, SearchOptions};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Read;
+
+/// Configures how many records around each matching record [`find_records`] returns, like grep's `-B`, `-A` and `-C`.
+///
+/// The default returns the matching records only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ContextOptions {
+    pub(crate) before: usize,
+    pub(crate) after: usize,
+}
+
+impl ContextOptions {
+    /// Creates a new set of context options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of records returned before each matching record. The default is `0`.
+    pub fn before(mut self, records: usize) -> Self {
+        self.before = records;
+        self
+    }
+
+    /// Sets the number of records returned after each matching record. The default is `0`.
+    pub fn after(mut self, records: usize) -> Self {
+        self.after = records;
+        self
+    }
+
+    /// Sets the number of records returned both before and after each matching record.
+    pub fn context(self, records: usize) -> Self {
+        self.before(records).after(records)
+    }
+}
+
+/// A whole record returned by [`find_records`], either matching or surrounding a matching record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextRecord {
+    /// The 1-based number of the data record (the header row is not counted).
+    pub record: u64,
+    /// The 1-based line number where the record starts in the input.
+    pub line: u64,
+    /// Whether the record matches: a selected field matches the pattern or, for
+    /// [`query_records`](crate::query_records), the record satisfies the query. `false` for context records.
+    pub matched: bool,
+    /// The fields of the record.
+    pub fields: Vec<String>,
+}
+
+/// The records returned by [`find_records`], with the header row of the input.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RecordMatches {
+    /// The header row of the input, when it has one.
+    pub headers: Option<Vec<String>>,
+    /// The matching and context records, in input order and without repetitions. A gap in the record numbers
+    /// separates two groups of context.
+    pub records: Vec<ContextRecord>,
+}
+
+/// Finds the whole records of the CSV file in which a selected field matches a pattern, together with the records
+/// surrounding them.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+/// * `context` - The [`ContextOptions`] describing how many surrounding records are returned.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RecordMatches`] if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// if a selected column does not exist, or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{find_records, ContextOptions, SearchOptions};
+///
+/// let found = find_records("app_log.csv", "ERROR", &SearchOptions::default(), &ContextOptions::new().context(2))
+///     .unwrap();
+/// ```
+pub fn find_records<P: Into<Pattern>>(
+    file_path: &str,
+    pattern: P,
+    options: &SearchOptions,
+    context: &ContextOptions,
+) -> Result<RecordMatches, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let matcher = options.matcher(&pattern.into())?;
+    let file = File::open(file_path)?;
+    context_records(file, Some(&matcher), options, context)
+}
+
+/// Finds the whole records of CSV data read from any `std::io::Read` source in which a selected field matches a
+/// pattern, together with the records surrounding them.
+///
+/// Only the context window is buffered, so memory use does not grow with the input size beyond the returned records.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+/// * `context` - The [`ContextOptions`] describing how many surrounding records are returned.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RecordMatches`] if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, if a selected column does
+/// not exist, or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_records_in, ContextOptions, SearchOptions};
+///
+/// let data = "level,message\ninfo,start\nwarn,disk\nerror,crash\ninfo,retry\ninfo,done\n";
+/// let context = ContextOptions::new().before(1).after(1);
+/// let found = find_records_in(data.as_bytes(), "^error$", &SearchOptions::new().column("level"), &context).unwrap();
+///
+/// assert_eq!(found.headers, Some(vec!["level".to_string(), "message".to_string()]));
+/// let records: Vec<(u64, bool)> = found.records.iter().map(|r| (r.record, r.matched)).collect();
+/// assert_eq!(records, vec![(2, false), (3, true), (4, false)]);
+/// ```
+pub fn find_records_in<R: Read, P: Into<Pattern>>(
+    reader: R,
+    pattern: P,
+    options: &SearchOptions,
+    context: &ContextOptions,
+) -> Result<RecordMatches, CoincidenceError> {
+    let matcher = options.matcher(&pattern.into())?;
+    context_records(reader, Some(&matcher), options, context)
+}
+
+/// Collects the records selected by the options' filters and, when there is a matcher, with a selected field it
+/// matches, together with their context.
+pub(crate) fn context_records<R: Read>(
+    reader: R,
+    matcher: Option<&Matcher>,
+    options: &SearchOptions,
+    context: &ContextOptions,
+) -> Result<RecordMatches, CoincidenceError> {
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let headers = options.read_headers(&mut rdr)?;
+    let filter = options.column_filter(headers.as_ref())?;
+    let record_filter = options.record_filter(headers.as_ref())?;
+
+    let mut found = RecordMatches {
+        headers: headers.as_ref().map(|h| h.iter().map(str::to_string).collect()),
+        records: Vec::new(),
+    };
+    let mut before: VecDeque<ContextRecord> = VecDeque::new();
+    let mut after = 0;
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = result?;
+        let matched = record_filter.is_selected(&record)
+            && match matcher {
+                Some(matcher) => record
+                    .iter()
+                    .enumerate()
+                    .any(|(column, field)| filter.is_selected(column) && matcher.find(field).is_some()),
+                None => true,
+            };
+
+        if !matched && after == 0 && context.before == 0 {
+            continue;
+        }
+
+        let context_record = ContextRecord {
+            record: index as u64 + 1,
+            line: record.position().map_or(0, |position| position.line()),
+            matched,
+            fields: record.iter().map(str::to_string).collect(),
+        };
+
+        if matched {
+            found.records.extend(before.drain(..));
+            found.records.push(context_record);
+            after = context.after;
+        } else if after > 0 {
+            found.records.push(context_record);
+            after -= 1;
+        } else {
+            if before.len() == context.before {
+                before.pop_front();
+            }
+            before.push_back(context_record);
+        }
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(found: &RecordMatches) -> Vec<(u64, bool)> {
+        found.records.iter().map(|r| (r.record, r.matched)).collect()
+    }
+
+    #[test]
+    fn test_matching_records_only() {
+        let log = "level,message\ninfo,start\nerror,disk full\ninfo,retry\nerror,crash\n";
+        let found = find_records_in(log.as_bytes(), "^error$", &SearchOptions::default(), &ContextOptions::new());
+
+        assert_eq!(numbered(&found.unwrap()), vec![(2, true), (4, true)]);
+    }
+
+    #[test]
+    fn test_overlapping_context_is_not_repeated() {
+        let log = "level\ninfo\ninfo\nerror\ninfo\ninfo\ninfo\nerror\nerror\ninfo\ninfo\ninfo\n";
+        let context = ContextOptions::new().context(2);
+        let found = find_records_in(log.as_bytes(), "^error$", &SearchOptions::default(), &context).unwrap();
+
+        assert_eq!(
+            numbered(&found),
+            vec![
+                (1, false),
+                (2, false),
+                (3, true),
+                (4, false),
+                (5, false),
+                (6, false),
+                (7, true),
+                (8, true),
+                (9, false),
+                (10, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_context_gaps_and_headerless_input() {
+        let log = "boot\nwarn: fan\nok\nok\nwarn: disk\nok\n";
+        let options = SearchOptions::new().has_headers(false);
+        let found = find_records_in(log.as_bytes(), "^warn", &options, &ContextOptions::new().after(1)).unwrap();
+
+        assert_eq!(found.headers, None);
+        assert_eq!(numbered(&found), vec![(2, true), (3, false), (5, true), (6, false)]);
+        assert_eq!(found.records[2].line, 5);
+    }
+
+    #[test]
+    fn test_huge_context_is_bounded_by_the_input() {
+        let log = "level\ninfo\nerror\n";
+        let context = ContextOptions::new().context(usize::MAX);
+        let found = find_records_in(log.as_bytes(), "^error$", &SearchOptions::default(), &context).unwrap();
+
+        assert_eq!(numbered(&found), vec![(1, false), (2, true)]);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::pattern::Matcher;
+use crate::{validate_csv_extension, CoincidenceError, Pattern, PatternCount, PatternSet, SearchOptions};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+
+/// What [`count_coincidences_with`](crate::count_coincidences_with) and
+/// [`count_coincidences_in`](crate::count_coincidences_in) count.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_coincidences_in, CountMode, SearchOptions};
+///
+/// let data = "letters,more\n\"a,a,a\",a\nb,b\n";
+///
+/// let options = SearchOptions::new().count_mode(CountMode::Fields);
+/// assert_eq!(count_coincidences_in(data.as_bytes(), "a", &options).unwrap(), 2);
+///
+/// let options = SearchOptions::new().count_mode(CountMode::Records);
+/// assert_eq!(count_coincidences_in(data.as_bytes(), "a", &options).unwrap(), 1);
+///
+/// let options = SearchOptions::new().count_mode(CountMode::Occurrences);
+/// assert_eq!(count_coincidences_in(data.as_bytes(), "a", &options).unwrap(), 4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CountMode {
+    /// Counts the fields containing at least one match.
+    #[default]
+    Fields,
+    /// Counts the records containing at least one matching field.
+    Records,
+    /// Counts every non-overlapping match, so a field matching three times counts three times.
+    Occurrences,
+}
+
+/// The result of counting a pattern over CSV data, with every [`CountMode`] computed in the same pass and broken
+/// down by column, by pattern and, optionally, by matched value.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_report_in, SearchOptions};
+///
+/// let data = "name,email\nJhon,jhon@test.com\nMarta,marta@example.com\nLuis,luis@test.com\n";
+/// let options = SearchOptions::new().top_values(1);
+/// let report = count_report_in(data.as_bytes(), r"@\w+\.com", &options).unwrap();
+///
+/// assert_eq!(report.columns[1].header.as_deref(), Some("email"));
+/// assert_eq!(report.columns[1].fields, 3);
+/// assert_eq!(report.top_values[0].value, "@test.com");
+/// assert_eq!(report.top_values[0].count, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CountReport {
+    /// The number of fields containing at least one match.
+    pub fields: usize,
+    /// The number of records containing at least one matching field.
+    pub records: usize,
+    /// The total number of non-overlapping matches.
+    pub occurrences: usize,
+    /// The number of data records read (the header row is not counted).
+    pub records_scanned: usize,
+    /// The number of fields searched, which excludes the fields of columns left out by the column selection.
+    pub fields_scanned: usize,
+    /// The counts of every searched column, in column order.
+    pub columns: Vec<ColumnCount>,
+    /// The counts of every pattern, in set order, when counting a [`PatternSet`]; empty for a single pattern.
+    pub patterns: Vec<PatternCount>,
+    /// The most frequent matched texts, most frequent first (ties in value order), up to the number set with
+    /// [`SearchOptions::top_values`]; empty by default.
+    pub top_values: Vec<ValueCount>,
+}
+
+/// The counts of a single column in a [`CountReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnCount {
+    /// The zero-based index of the column.
+    pub column: usize,
+    /// The header name of the column, when the input has a header row.
+    pub header: Option<String>,
+    /// The number of fields of the column containing at least one match.
+    pub fields: usize,
+    /// The total number of non-overlapping matches in the column.
+    pub occurrences: usize,
+}
+
+/// The number of times a distinct text was matched, listed in [`CountReport::top_values`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueCount {
+    /// The matched text.
+    pub value: String,
+    /// The number of occurrences of the text.
+    pub count: usize,
+}
+
+impl CountReport {
+    /// Returns the count selected by the given mode.
+    pub fn get(&self, mode: CountMode) -> usize {
+        match mode {
+            CountMode::Fields => self.fields,
+            CountMode::Records => self.records,
+            CountMode::Occurrences => self.occurrences,
+        }
+    }
+}
+
+/// Counts the matches of a specific pattern in the CSV file, reporting matching fields, matching records and total
+/// occurrences at once.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `patron` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CountReport`] if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{count_report, SearchOptions};
+///
+/// let report = count_report("example.csv", r"\bexample\b", &SearchOptions::default()).unwrap();
+/// println!("{} occurrences in {} of {} records", report.occurrences, report.records, report.records_scanned);
+/// ```
+pub fn count_report<P: Into<Pattern>>(
+    file_path: &str,
+    patron: P,
+    options: &SearchOptions,
+) -> Result<CountReport, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let matcher = options.matcher(&patron.into())?;
+    let file = File::open(file_path)?;
+    count_matches(file, &matcher, options)
+}
+
+/// Counts the matches of a specific pattern in CSV data read from any `std::io::Read` source, reporting matching
+/// fields, matching records and total occurrences at once.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `patron` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CountReport`] if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, or if the regular
+/// expression pattern is invalid.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_report_in, SearchOptions};
+///
+/// let data = "letters,more\n\"a,a,a\",a\nb,b\n";
+/// let report = count_report_in(data.as_bytes(), "a", &SearchOptions::default()).unwrap();
+///
+/// assert_eq!((report.fields, report.records, report.occurrences), (2, 1, 4));
+/// assert_eq!((report.records_scanned, report.fields_scanned), (2, 4));
+/// ```
+pub fn count_report_in<R: Read, P: Into<Pattern>>(
+    reader: R,
+    patron: P,
+    options: &SearchOptions,
+) -> Result<CountReport, CoincidenceError> {
+    let matcher = options.matcher(&patron.into())?;
+    count_matches(reader, &matcher, options)
+}
+
+/// Counts the matches of every pattern of a [`PatternSet`] in a single pass over the CSV file.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `patterns` - The set of patterns to match against the CSV records.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CountReport`], with a [`PatternCount`] per pattern, if successful, or an error if
+/// there is any issue during processing. A field matched by several patterns counts once in the `fields` totals.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read or contains malformed records.
+pub fn count_report_many(
+    file_path: &str,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<CountReport, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    count_set_matches(file, patterns, options)
+}
+
+/// Counts the matches of every pattern of a [`PatternSet`] in a single pass over CSV data read from any
+/// `std::io::Read` source.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `patterns` - The set of patterns to match against the CSV records.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CountReport`], with a [`PatternCount`] per pattern, if successful, or an error if
+/// there is any issue during processing. A field matched by several patterns counts once in the `fields` totals.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read or contains malformed records.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_report_many_in, PatternSet, SearchOptions};
+///
+/// let patterns = PatternSet::labeled([("digit", r"\d"), ("upper", "[A-Z]")]).unwrap();
+/// let data = "code\nA1B2\nxyz\n";
+/// let report = count_report_many_in(data.as_bytes(), &patterns, &SearchOptions::default()).unwrap();
+///
+/// assert_eq!((report.fields, report.occurrences), (1, 4));
+/// assert_eq!((report.patterns[0].label.as_str(), report.patterns[0].occurrences), ("digit", 2));
+/// ```
+pub fn count_report_many_in<R: Read>(
+    reader: R,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<CountReport, CoincidenceError> {
+    count_set_matches(reader, patterns, options)
+}
+
+pub(crate) fn count_matches<R: Read>(
+    reader: R,
+    matcher: &Matcher,
+    options: &SearchOptions,
+) -> Result<CountReport, CoincidenceError> {
+    tally(reader, options, Vec::new(), |field, hits| {
+        hits.extend(matcher.find_iter(field).map(|span| (0, span)));
+    })
+}
+
+pub(crate) fn count_set_matches<R: Read>(
+    reader: R,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<CountReport, CoincidenceError> {
+    let counts = (0..patterns.len())
+        .map(|pattern| PatternCount {
+            pattern,
+            label: patterns.label(pattern).to_string(),
+            count: 0,
+            occurrences: 0,
+        })
+        .collect();
+    let patterns = patterns.compile(options.match_mode)?;
+
+    tally(reader, options, counts, |field, hits| {
+        for pattern in patterns.matching(field, options.invert) {
+            match options.invert {
+                true => hits.push((pattern, 0..field.len())),
+                false => hits.extend(patterns.regexes[pattern].find_iter(field).map(|m| (pattern, m.range()))),
+            }
+        }
+    })
+}
+
+/// Reads every record, calling `find` to collect the `(pattern, span)` hits of each selected field, grouped by
+/// pattern, and adds them up into a report.
+fn tally<R, F>(
+    reader: R,
+    options: &SearchOptions,
+    patterns: Vec<PatternCount>,
+    mut find: F,
+) -> Result<CountReport, CoincidenceError>
+where
+    R: Read,
+    F: FnMut(&str, &mut Vec<(usize, Range<usize>)>),
+{
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let headers = options.read_headers(&mut rdr)?;
+    let filter = options.column_filter(headers.as_ref())?;
+    let record_filter = options.record_filter(headers.as_ref())?;
+
+    let mut report = CountReport {
+        patterns,
+        ..CountReport::default()
+    };
+    let mut columns: Vec<Option<ColumnCount>> = Vec::new();
+    let mut values: HashMap<String, usize> = HashMap::new();
+    let mut hits = Vec::new();
+
+    for result in rdr.records() {
+        let record = result?;
+        if !record_filter.is_selected(&record) {
+            continue;
+        }
+
+        let mut record_matched = false;
+        report.records_scanned += 1;
+
+        for (column, field) in record.iter().enumerate() {
+            if !filter.is_selected(column) {
+                continue;
+            }
+
+            if columns.len() <= column {
+                columns.resize(column + 1, None);
+            }
+            let column_count = columns[column].get_or_insert_with(|| ColumnCount {
+                column,
+                header: headers.as_ref().and_then(|h| h.get(column)).map(str::to_string),
+                fields: 0,
+                occurrences: 0,
+            });
+
+            report.fields_scanned += 1;
+            hits.clear();
+            find(field, &mut hits);
+            if hits.is_empty() {
+                continue;
+            }
+
+            record_matched = true;
+            report.fields += 1;
+            report.occurrences += hits.len();
+            column_count.fields += 1;
+            column_count.occurrences += hits.len();
+
+            for (i, (pattern, span)) in hits.iter().enumerate() {
+                if let Some(pattern_count) = report.patterns.get_mut(*pattern) {
+                    if i == 0 || hits[i - 1].0 != *pattern {
+                        pattern_count.count += 1;
+                    }
+                    pattern_count.occurrences += 1;
+                }
+
+                if options.top_values > 0 {
+                    *values.entry(field[span.clone()].to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        if record_matched {
+            report.records += 1;
+        }
+    }
+
+    report.columns = columns.into_iter().flatten().collect();
+    report.top_values = values.into_iter().map(|(value, count)| ValueCount { value, count }).collect();
+    report.top_values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    report.top_values.truncate(options.top_values);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_respects_column_selection() {
+        let data = "a,b,c\nxx,x,\n,,x\n";
+        let options = SearchOptions::new().columns([0, 1]);
+        let report = count_report_in(data.as_bytes(), "x", &options).unwrap();
+
+        assert_eq!((report.fields, report.records, report.occurrences), (2, 1, 3));
+        assert_eq!((report.records_scanned, report.fields_scanned), (2, 4));
+
+        let columns: Vec<(usize, Option<&str>, usize, usize)> = report
+            .columns
+            .iter()
+            .map(|c| (c.column, c.header.as_deref(), c.fields, c.occurrences))
+            .collect();
+        assert_eq!(columns, vec![(0, Some("a"), 1, 2), (1, Some("b"), 1, 1)]);
+    }
+
+    #[test]
+    fn test_inverted_report_counts_non_matching_fields() {
+        let data = "name,phone\nJhon,555-1234\nMarta,n/a\nLuis,n/a\nAna,\n";
+        let options = SearchOptions::new().column("phone").invert(true).top_values(1);
+        let report = count_report_in(data.as_bytes(), r"^\d{3}-\d{4}$", &options).unwrap();
+
+        assert_eq!((report.fields, report.records, report.occurrences), (3, 3, 3));
+        assert_eq!(report.top_values, vec![ValueCount { value: "n/a".to_string(), count: 2 }]);
+    }
+
+    #[test]
+    fn test_occurrences_with_literals() {
+        let data = "text\nfoo bar foo\nbaz\n";
+        let pattern = Pattern::literals(["foo", "baz"]);
+        let report = count_report_in(data.as_bytes(), pattern, &SearchOptions::default()).unwrap();
+
+        assert_eq!(report.get(CountMode::Occurrences), 3);
+        assert_eq!(report.get(CountMode::Records), 2);
+    }
+
+    #[test]
+    fn test_pattern_breakdown_counts_fields_once_per_pattern() {
+        let patterns = PatternSet::new(["a", "b"]).unwrap();
+        let data = "x,y\naab,b\nc,a\n";
+        let report = count_report_many_in(data.as_bytes(), &patterns, &SearchOptions::default()).unwrap();
+
+        let totals: Vec<(usize, usize)> = report.patterns.iter().map(|p| (p.count, p.occurrences)).collect();
+        assert_eq!(totals, vec![(2, 3), (2, 2)]);
+        assert_eq!((report.fields, report.records, report.occurrences), (3, 2, 5));
+    }
+
+    #[test]
+    fn test_top_values_are_ranked_and_truncated() {
+        let data = "tag\nred blue\nblue\ngreen red\nblue\n";
+        let options = SearchOptions::new().top_values(2);
+        let report = count_report_in(data.as_bytes(), r"\w+", &options).unwrap();
+
+        let top: Vec<(&str, usize)> = report.top_values.iter().map(|v| (v.value.as_str(), v.count)).collect();
+        assert_eq!(top, vec![("blue", 3), ("red", 2)]);
+
+        let report = count_report_in(data.as_bytes(), r"\w+", &SearchOptions::default()).unwrap();
+        assert!(report.top_values.is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::columns::{resolve_columns, ColumnSelector};
+use crate::{validate_csv_extension, CoincidenceError, SearchOptions};
+use csv::StringRecord;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::slice;
+
+/// Which records [`find_cross_coincidences`] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrossMode {
+    /// Every pair of left and right records sharing a key.
+    #[default]
+    Inner,
+    /// The left records whose key does not appear in the right input.
+    LeftOnly,
+    /// The right records whose key does not appear in the left input.
+    RightOnly,
+}
+
+/// A key found by [`find_cross_coincidences`], with the records it was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossMatch {
+    /// The key values, one per selected column.
+    pub key: Vec<String>,
+    /// The 1-based number of the left data record, or `None` for [`CrossMode::RightOnly`] results.
+    pub left: Option<u64>,
+    /// The 1-based number of the right data record, or `None` for [`CrossMode::LeftOnly`] results.
+    pub right: Option<u64>,
+}
+
+/// Finds the values of a column of one CSV file that also appear in a column of another.
+///
+/// The right file is loaded into a hash index of its keys and the left file is streamed against it, so the smaller
+/// file should be passed as `right_path`. Keys are compared exactly; a selector matching several columns (a range or
+/// header pattern) builds a composite key from them.
+///
+/// # Arguments
+///
+/// * `left_path` - A string slice representing the file path to the left CSV file.
+/// * `right_path` - A string slice representing the file path to the right CSV file.
+/// * `left_column` - The column(s) holding the key in the left file.
+/// * `right_column` - The column(s) holding the key in the right file.
+/// * `options` - The [`SearchOptions`] describing how both files are read and which of their records are compared.
+/// * `mode` - Which records to report.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CrossMatch`] values, in left file order for [`CrossMode::Inner`] and
+/// [`CrossMode::LeftOnly`] and in right file order for [`CrossMode::RightOnly`], or an error if there is any issue
+/// during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either file is not a valid CSV file, cannot be read, contains malformed records,
+/// if a key column does not exist, or if the keys of both files have a different number of columns.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{find_cross_coincidences, CrossMode, SearchOptions};
+///
+/// let blacklisted = find_cross_coincidences(
+///     "orders.csv",
+///     "blacklist.csv",
+///     "customer_id",
+///     "id",
+///     &SearchOptions::default(),
+///     CrossMode::Inner,
+/// )
+/// .unwrap();
+/// ```
+pub fn find_cross_coincidences<A, B>(
+    left_path: &str,
+    right_path: &str,
+    left_column: A,
+    right_column: B,
+    options: &SearchOptions,
+    mode: CrossMode,
+) -> Result<Vec<CrossMatch>, CoincidenceError>
+where
+    A: Into<ColumnSelector>,
+    B: Into<ColumnSelector>,
+{
+    validate_csv_extension(left_path)?;
+    validate_csv_extension(right_path)?;
+
+    let left = File::open(left_path)?;
+    let right = File::open(right_path)?;
+    find_cross_coincidences_in(left, right, left_column, right_column, options, mode)
+}
+
+/// Finds the values of a column of CSV data that also appear in a column of other CSV data, both read from any
+/// `std::io::Read` source.
+///
+/// The right input is loaded into a hash index of its keys and the left input is streamed against it. Keys are
+/// compared exactly; a selector matching several columns (a range or header pattern) builds a composite key from
+/// them.
+///
+/// # Arguments
+///
+/// * `left` - The source of the left CSV data.
+/// * `right` - The source of the right CSV data.
+/// * `left_column` - The column(s) holding the key in the left data.
+/// * `right_column` - The column(s) holding the key in the right data.
+/// * `options` - The [`SearchOptions`] describing how both inputs are read and which of their records are compared.
+/// * `mode` - Which records to report.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CrossMatch`] values, in left input order for [`CrossMode::Inner`] and
+/// [`CrossMode::LeftOnly`] and in right input order for [`CrossMode::RightOnly`], or an error if there is any issue
+/// during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either input cannot be read, contains malformed records, if a key column does not
+/// exist, or if the keys of both inputs have a different number of columns.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_cross_coincidences_in, CrossMode, SearchOptions};
+///
+/// let orders = "order,customer_id\n1,c7\n2,c3\n3,c7\n";
+/// let blacklist = "id,reason\nc7,fraud\nc9,chargeback\n";
+///
+/// let found = find_cross_coincidences_in(
+///     orders.as_bytes(),
+///     blacklist.as_bytes(),
+///     "customer_id",
+///     "id",
+///     &SearchOptions::default(),
+///     CrossMode::Inner,
+/// )
+/// .unwrap();
+///
+/// let pairs: Vec<_> = found.iter().map(|m| (m.left, m.right)).collect();
+/// assert_eq!(pairs, vec![(Some(1), Some(1)), (Some(3), Some(1))]);
+/// ```
+pub fn find_cross_coincidences_in<L, R, A, B>(
+    left: L,
+    right: R,
+    left_column: A,
+    right_column: B,
+    options: &SearchOptions,
+    mode: CrossMode,
+) -> Result<Vec<CrossMatch>, CoincidenceError>
+where
+    L: Read,
+    R: Read,
+    A: Into<ColumnSelector>,
+    B: Into<ColumnSelector>,
+{
+    let mut left_rdr = options.reader_builder().from_reader(left);
+    let mut right_rdr = options.reader_builder().from_reader(right);
+    let left_headers = options.read_headers(&mut left_rdr)?;
+    let right_headers = options.read_headers(&mut right_rdr)?;
+    let left_columns = resolve_columns(slice::from_ref(&left_column.into()), left_headers.as_ref())?;
+    let right_columns = resolve_columns(slice::from_ref(&right_column.into()), right_headers.as_ref())?;
+    let left_filter = options.record_filter(left_headers.as_ref())?;
+    let right_filter = options.record_filter(right_headers.as_ref())?;
+
+    if left_columns.len() != right_columns.len() {
+        return Err(CoincidenceError::KeyMismatch {
+            left: left_columns.len(),
+            right: right_columns.len(),
+        });
+    }
+
+    let mut index: HashMap<Vec<String>, (Vec<u64>, bool)> = HashMap::new();
+    for (record_index, result) in right_rdr.records().enumerate() {
+        let record = result?;
+        if !right_filter.is_selected(&record) {
+            continue;
+        }
+        let (records, _) = index.entry(key(&record, &right_columns)?).or_default();
+        records.push(record_index as u64 + 1);
+    }
+
+    let mut matches = Vec::new();
+    for (record_index, result) in left_rdr.records().enumerate() {
+        let record = result?;
+        if !left_filter.is_selected(&record) {
+            continue;
+        }
+        let left = Some(record_index as u64 + 1);
+        let key = key(&record, &left_columns)?;
+
+        match (index.get_mut(&key), mode) {
+            (Some((records, _)), CrossMode::Inner) => matches.extend(records.iter().map(|&right| CrossMatch {
+                key: key.clone(),
+                left,
+                right: Some(right),
+            })),
+            (None, CrossMode::LeftOnly) => matches.push(CrossMatch { key, left, right: None }),
+            (Some((_, seen)), CrossMode::RightOnly) => *seen = true,
+            _ => {}
+        }
+    }
+
+    if mode == CrossMode::RightOnly {
+        matches = index
+            .into_iter()
+            .filter(|(_, (_, seen))| !seen)
+            .flat_map(|(key, (records, _))| {
+                records.into_iter().map(move |right| CrossMatch {
+                    key: key.clone(),
+                    left: None,
+                    right: Some(right),
+                })
+            })
+            .collect();
+        matches.sort_by_key(|m| m.right);
+    }
+
+    Ok(matches)
+}
+
+/// Extracts the values of the given columns from a record.
+pub(crate) fn key(record: &StringRecord, columns: &[usize]) -> Result<Vec<String>, CoincidenceError> {
+    columns
+        .iter()
+        .map(|&column| {
+            record.get(column).map(str::to_string).ok_or(CoincidenceError::ColumnOutOfRange {
+                index: column,
+                len: record.len(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+
+    const EMPLOYEES: &str = "id,name\n1,Jhon\n2,Marta\n3,Luis\n";
+    const BADGES: &str = "code,id\nx,3\ny,1\nz,9\nw,3\n";
+
+    fn cross_badges(mode: CrossMode) -> Vec<(Vec<String>, Option<u64>, Option<u64>)> {
+        find_cross_coincidences_in(EMPLOYEES.as_bytes(), BADGES.as_bytes(), "id", 1, &SearchOptions::default(), mode)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.key, m.left, m.right))
+            .collect()
+    }
+
+    #[test]
+    fn test_inner_reports_every_pair() {
+        let key = |k: &str| vec![k.to_string()];
+        assert_eq!(
+            cross_badges(CrossMode::Inner),
+            vec![(key("1"), Some(1), Some(2)), (key("3"), Some(3), Some(1)), (key("3"), Some(3), Some(4))]
+        );
+    }
+
+    #[test]
+    fn test_one_sided_modes() {
+        assert_eq!(cross_badges(CrossMode::LeftOnly), vec![(vec!["2".to_string()], Some(2), None)]);
+        assert_eq!(cross_badges(CrossMode::RightOnly), vec![(vec!["9".to_string()], None, Some(3))]);
+    }
+
+    #[test]
+    fn test_composite_keys_and_missing_columns() {
+        let left = "a,b\n1,x\n1,y\n";
+        let right = "a,b\n1,y\n";
+        let options = SearchOptions::default();
+        let mode = CrossMode::Inner;
+
+        let found = find_cross_coincidences_in(left.as_bytes(), right.as_bytes(), 0..2, 0..2, &options, mode);
+        assert_eq!(found.unwrap()[0].left, Some(2));
+
+        let result = find_cross_coincidences_in(left.as_bytes(), right.as_bytes(), "c", "a", &options, mode);
+        assert!(matches!(result, Err(CoincidenceError::UnknownColumn(_))));
+
+        let result = find_cross_coincidences_in(left.as_bytes(), left.as_bytes(), 0..2, "a", &options, mode);
+        assert!(matches!(result, Err(CoincidenceError::KeyMismatch { left: 2, right: 1 })));
+    }
+
+    #[test]
+    fn test_filtered_records_are_not_compared() {
+        let options = SearchOptions::new().filter(Query::parse("id != 3").unwrap());
+        let found = find_cross_coincidences_in(
+            EMPLOYEES.as_bytes(),
+            BADGES.as_bytes(),
+            "id",
+            "id",
+            &options,
+            CrossMode::LeftOnly,
+        );
+        let left: Vec<Option<u64>> = found.unwrap().iter().map(|m| m.left).collect();
+
+        assert_eq!(left, vec![Some(2)]);
+    }
+}
@@ -0,0 +1,721 @@
+use crate::columns::{resolve_columns, ColumnFilter, ColumnSelector};
+use crate::cross::key;
+use crate::duplicates::{parse_record_number, partition_of};
+use crate::json::json_string;
+use crate::query::RecordFilter;
+use crate::{validate_csv_extension, CoincidenceError, SearchOptions};
+use csv::StringRecord;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// How a record changed between the old and new versions of a CSV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The key only exists in the new version.
+    Added,
+    /// The key only exists in the old version.
+    Removed,
+    /// The key exists in both versions with different values in at least one compared column.
+    Modified,
+}
+
+impl ChangeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+/// The old and new values of one cell of a [`RowChange`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellChange {
+    /// The header name of the column, when both versions have a header row.
+    pub header: Option<String>,
+    /// The zero-based index of the column in the old version, or `None` if it does not exist there.
+    pub old_column: Option<usize>,
+    /// The zero-based index of the column in the new version, or `None` if it does not exist there.
+    pub new_column: Option<usize>,
+    /// The old value, or `None` for added records and columns.
+    pub old: Option<String>,
+    /// The new value, or `None` for removed records and columns.
+    pub new: Option<String>,
+}
+
+/// A record that was added, removed or modified between two versions of a CSV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowChange {
+    /// How the record changed.
+    pub kind: ChangeKind,
+    /// The key values, one per key column.
+    pub key: Vec<String>,
+    /// The 1-based number of the data record in the old version, or `None` for added records.
+    pub old_record: Option<u64>,
+    /// The 1-based number of the data record in the new version, or `None` for removed records.
+    pub new_record: Option<u64>,
+    /// Every compared cell of added and removed records, or only the cells that differ for modified records.
+    pub cells: Vec<CellChange>,
+}
+
+/// The format [`write_diff`] writes the changes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffFormat {
+    /// One CSV row per changed cell, with the columns `change`, the key values (`key`, or `key_1`, `key_2`, ... for
+    /// composite keys), `old_record`, `new_record`, `column`, `old_value` and `new_value`.
+    #[default]
+    Csv,
+    /// A JSON array with one object per [`RowChange`].
+    Json,
+}
+
+/// Configures how [`diff_csv`] holds the old version of the file while comparing.
+///
+/// The default indexes every record of the old version in memory.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{diff_csv_in, DiffOptions, SearchOptions};
+///
+/// let old = "id,name\n1,Jhon\n2,Marta\n";
+/// let new = "id,name\n2,Marta\n1,John\n";
+/// let diff = DiffOptions::new().spill_partitions(4);
+/// let found = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &SearchOptions::default(), &diff).unwrap();
+///
+/// assert_eq!(found.changes.len(), 1);
+/// assert_eq!(found.changes[0].new_record, Some(2));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub(crate) spill_partitions: usize,
+}
+
+impl DiffOptions {
+    /// Creates a new set of diff options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Streams both versions into the given number of temporary files, partitioned by the hash of the key, and
+    /// compares one partition at a time, so only about `1 / partitions` of the old version is held in memory at once.
+    /// The default is `0`, which indexes the whole old version in memory.
+    pub fn spill_partitions(mut self, partitions: usize) -> Self {
+        self.spill_partitions = partitions;
+        self
+    }
+}
+
+/// The changes found by [`diff_csv`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CsvDiff {
+    /// The number of key columns, which [`write_diff`] writes as key columns of the CSV output even when there are no
+    /// changes.
+    pub key_columns: usize,
+    /// The added and modified records in new file order, followed by the removed records in old file order.
+    pub changes: Vec<RowChange>,
+}
+
+/// Compares two versions of a CSV file record by record, matching records by key.
+///
+/// When both files have a header row, columns are matched by name, so reordered columns are not reported as changes;
+/// otherwise they are matched by index. The column selection of `options` restricts which columns are compared.
+/// Records sharing a key are paired in file order.
+///
+/// Both files are read once. By default the old file is indexed in memory while the new file is streamed against it;
+/// use [`DiffOptions::spill_partitions`] to bound the memory used for large files.
+///
+/// # Arguments
+///
+/// * `old_path` - A string slice representing the file path to the old version of the CSV file.
+/// * `new_path` - A string slice representing the file path to the new version of the CSV file.
+/// * `key_columns` - The columns identifying a record in both versions.
+/// * `options` - The [`SearchOptions`] describing how both files are read and which columns and records are compared.
+/// * `diff` - The [`DiffOptions`] describing how the old file is held while comparing.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CsvDiff`], or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either file is not a valid CSV file, cannot be read, contains malformed records,
+/// if a key or selected column does not exist or resolves to a different number of columns in each file, or if a spill
+/// file cannot be written or read back.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{diff_csv, write_diff, DiffFormat, DiffOptions, SearchOptions};
+///
+/// let options = SearchOptions::default();
+/// let diff = diff_csv("export_monday.csv", "export_tuesday.csv", ["id"], &options, &DiffOptions::new()).unwrap();
+/// write_diff(&diff, std::io::stdout().lock(), DiffFormat::Json, &options).unwrap();
+/// ```
+pub fn diff_csv<K>(
+    old_path: &str,
+    new_path: &str,
+    key_columns: K,
+    options: &SearchOptions,
+    diff: &DiffOptions,
+) -> Result<CsvDiff, CoincidenceError>
+where
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(old_path)?;
+    validate_csv_extension(new_path)?;
+
+    let old = File::open(old_path)?;
+    let new = File::open(new_path)?;
+    diff_csv_in(old, new, key_columns, options, diff)
+}
+
+/// Compares two versions of CSV data read from `std::io::Read` sources record by record, matching records by key.
+///
+/// Columns are matched and memory is used as described in [`diff_csv`].
+///
+/// # Arguments
+///
+/// * `old` - The source of the old version of the CSV data.
+/// * `new` - The source of the new version of the CSV data.
+/// * `key_columns` - The columns identifying a record in both versions.
+/// * `options` - The [`SearchOptions`] describing how both inputs are read and which columns and records are compared.
+/// * `diff` - The [`DiffOptions`] describing how the old version is held while comparing.
+///
+/// # Returns
+///
+/// A `Result` containing the [`CsvDiff`], or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either input cannot be read, contains malformed records, if a key or selected
+/// column does not exist or resolves to a different number of columns in each input, or if a spill file cannot be
+/// written or read back.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{diff_csv_in, ChangeKind, DiffOptions, SearchOptions};
+///
+/// let old = "id,name,city\n1,Jhon,Madrid\n2,Marta,Paris\n";
+/// let new = "id,city,name\n1,Madrid,John\n3,Rome,Luis\n";
+/// let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &SearchOptions::default(), &DiffOptions::new())
+///     .unwrap();
+///
+/// let kinds: Vec<ChangeKind> = diff.changes.iter().map(|c| c.kind).collect();
+/// assert_eq!(kinds, vec![ChangeKind::Modified, ChangeKind::Added, ChangeKind::Removed]);
+///
+/// let cell = &diff.changes[0].cells[0];
+/// assert_eq!(cell.header.as_deref(), Some("name"));
+/// assert_eq!((cell.old.as_deref(), cell.new.as_deref()), (Some("Jhon"), Some("John")));
+/// ```
+pub fn diff_csv_in<O, N, K>(
+    old: O,
+    new: N,
+    key_columns: K,
+    options: &SearchOptions,
+    diff: &DiffOptions,
+) -> Result<CsvDiff, CoincidenceError>
+where
+    O: Read,
+    N: Read,
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    let key_columns: Vec<ColumnSelector> = key_columns.into_iter().map(Into::into).collect();
+
+    let mut old_rdr = options.reader_builder().from_reader(old);
+    let mut new_rdr = options.reader_builder().from_reader(new);
+    let old_headers = options.read_headers(&mut old_rdr)?;
+    let new_headers = options.read_headers(&mut new_rdr)?;
+    let old_keys = resolve_columns(&key_columns, old_headers.as_ref())?;
+    let new_keys = resolve_columns(&key_columns, new_headers.as_ref())?;
+    let old_filter = options.record_filter(old_headers.as_ref())?;
+    let new_filter = options.record_filter(new_headers.as_ref())?;
+    let layout = Layout::new(
+        old_headers.as_ref(),
+        new_headers.as_ref(),
+        options.column_filter(old_headers.as_ref())?,
+        options.column_filter(new_headers.as_ref())?,
+    );
+
+    if old_keys.len() != new_keys.len() {
+        return Err(CoincidenceError::KeyMismatch {
+            left: old_keys.len(),
+            right: new_keys.len(),
+        });
+    }
+
+    let old_records = keyed_records(&mut old_rdr, &old_keys, &old_filter);
+    let new_records = keyed_records(&mut new_rdr, &new_keys, &new_filter);
+    let mut changes = Vec::new();
+
+    match diff.spill_partitions {
+        0 => pair_records(old_records, new_records, &layout, &mut changes)?,
+        partitions => {
+            let old_partitions = spill(old_records, partitions)?;
+            let new_partitions = spill(new_records, partitions)?;
+            for (old, new) in old_partitions.into_iter().zip(new_partitions) {
+                let (mut old, mut new) = (spilled_reader(old), spilled_reader(new));
+                let old_records = read_spilled(&mut old, &old_keys);
+                pair_records(old_records, read_spilled(&mut new, &new_keys), &layout, &mut changes)?;
+            }
+            changes.sort_by_key(|change| match change.kind {
+                ChangeKind::Removed => (true, change.old_record),
+                _ => (false, change.new_record),
+            });
+        }
+    }
+
+    Ok(CsvDiff {
+        key_columns: old_keys.len(),
+        changes,
+    })
+}
+
+/// A selected record with its 1-based record number and key values.
+type Keyed = (u64, Vec<String>, StringRecord);
+
+/// Reads the records selected by the filter, with their record numbers and keys.
+fn keyed_records<'r, R: Read>(
+    rdr: &'r mut csv::Reader<R>,
+    keys: &'r [usize],
+    filter: &'r RecordFilter,
+) -> impl Iterator<Item = Result<Keyed, CoincidenceError>> + 'r {
+    rdr.records()
+        .enumerate()
+        .map(|(index, result)| {
+            let record = result?;
+            if !filter.is_selected(&record) {
+                return Ok(None);
+            }
+            Ok(Some((index as u64 + 1, key(&record, keys)?, record)))
+        })
+        .filter_map(Result::transpose)
+}
+
+/// Indexes the old records by key, streams the new records against them and appends the changes: added and modified
+/// records in new order, then removed records in old order.
+fn pair_records<O, N>(old: O, new: N, layout: &Layout, changes: &mut Vec<RowChange>) -> Result<(), CoincidenceError>
+where
+    O: Iterator<Item = Result<Keyed, CoincidenceError>>,
+    N: Iterator<Item = Result<Keyed, CoincidenceError>>,
+{
+    let mut old_records: Vec<(u64, Vec<String>, Option<StringRecord>)> = Vec::new();
+    let mut index: HashMap<Vec<String>, VecDeque<usize>> = HashMap::new();
+    for result in old {
+        let (number, key, record) = result?;
+        index.entry(key.clone()).or_default().push_back(old_records.len());
+        old_records.push((number, key, Some(record)));
+    }
+
+    for result in new {
+        let (number, key, record) = result?;
+        let new_record = Some(number);
+
+        match index.get_mut(&key).and_then(VecDeque::pop_front) {
+            Some(old_index) => {
+                let (old_number, _, old) = &mut old_records[old_index];
+                let old = old.take().expect("each old record is paired once");
+                let cells: Vec<CellChange> = layout
+                    .cells(Some(&old), Some(&record))
+                    .into_iter()
+                    .filter(|cell| cell.old != cell.new)
+                    .collect();
+
+                if !cells.is_empty() {
+                    changes.push(RowChange {
+                        kind: ChangeKind::Modified,
+                        key,
+                        old_record: Some(*old_number),
+                        new_record,
+                        cells,
+                    });
+                }
+            }
+            None => changes.push(RowChange {
+                kind: ChangeKind::Added,
+                key,
+                old_record: None,
+                new_record,
+                cells: layout.cells(None, Some(&record)),
+            }),
+        }
+    }
+
+    for (number, key, record) in old_records {
+        if let Some(record) = record {
+            changes.push(RowChange {
+                kind: ChangeKind::Removed,
+                key,
+                old_record: Some(number),
+                new_record: None,
+                cells: layout.cells(Some(&record), None),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes every record, prefixed with its record number, to one of `partitions` temporary files chosen by the hash of
+/// its key, so records sharing a key in both versions land in partitions with the same index.
+fn spill<I>(records: I, partitions: usize) -> Result<Vec<File>, CoincidenceError>
+where
+    I: Iterator<Item = Result<Keyed, CoincidenceError>>,
+{
+    let mut writers = (0..partitions)
+        .map(|_| Ok(csv::WriterBuilder::new().flexible(true).from_writer(tempfile::tempfile()?)))
+        .collect::<Result<Vec<_>, CoincidenceError>>()?;
+
+    for result in records {
+        let (number, key, record) = result?;
+        let number = number.to_string();
+        let row = std::iter::once(number.as_str()).chain(record.iter());
+        writers[partition_of(&key, partitions)].write_record(row)?;
+    }
+
+    writers
+        .into_iter()
+        .map(|writer| {
+            let mut file = writer.into_inner()?;
+            file.seek(SeekFrom::Start(0))?;
+            Ok(file)
+        })
+        .collect()
+}
+
+fn spilled_reader(file: File) -> csv::Reader<File> {
+    csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(file)
+}
+
+/// Reads back the records of a spill partition, recomputing their keys.
+fn read_spilled<'r>(
+    rdr: &'r mut csv::Reader<File>,
+    keys: &'r [usize],
+) -> impl Iterator<Item = Result<Keyed, CoincidenceError>> + 'r {
+    rdr.records().map(|result| {
+        let row = result?;
+        let number = parse_record_number(row.get(0).unwrap_or_default())?;
+        let record: StringRecord = row.iter().skip(1).collect();
+        Ok((number, key(&record, keys)?, record))
+    })
+}
+
+/// The compared columns of two versions of a file.
+enum Layout {
+    /// Columns matched by header name: `(header, old index, new index)`.
+    Named(Vec<(String, Option<usize>, Option<usize>)>),
+    /// Columns matched by index, restricted to the selected ones.
+    Indexed(ColumnFilter),
+}
+
+impl Layout {
+    fn new(
+        old_headers: Option<&StringRecord>,
+        new_headers: Option<&StringRecord>,
+        old_filter: ColumnFilter,
+        new_filter: ColumnFilter,
+    ) -> Self {
+        let (Some(old_headers), Some(new_headers)) = (old_headers, new_headers) else {
+            return Layout::Indexed(new_filter);
+        };
+
+        let old_position = |name: &str| old_headers.iter().position(|header| header == name);
+        let mut columns: Vec<(String, Option<usize>, Option<usize>)> = new_headers
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| new_filter.is_selected(column))
+            .map(|(column, name)| (name.to_string(), old_position(name), Some(column)))
+            .collect();
+
+        columns.extend(
+            old_headers
+                .iter()
+                .enumerate()
+                .filter(|&(column, name)| old_filter.is_selected(column) && !new_headers.iter().any(|h| h == name))
+                .map(|(column, name)| (name.to_string(), Some(column), None)),
+        );
+
+        Layout::Named(columns)
+    }
+
+    /// Returns every compared cell of the pair of records.
+    fn cells(&self, old: Option<&StringRecord>, new: Option<&StringRecord>) -> Vec<CellChange> {
+        let value = |record: Option<&StringRecord>, column: Option<usize>| {
+            record.zip(column).and_then(|(record, column)| record.get(column)).map(str::to_string)
+        };
+
+        match self {
+            Layout::Named(columns) => columns
+                .iter()
+                .map(|(header, old_column, new_column)| CellChange {
+                    header: Some(header.clone()),
+                    old_column: *old_column,
+                    new_column: *new_column,
+                    old: value(old, *old_column),
+                    new: value(new, *new_column),
+                })
+                .collect(),
+            Layout::Indexed(filter) => {
+                let width = old.map_or(0, StringRecord::len).max(new.map_or(0, StringRecord::len));
+                (0..width)
+                    .filter(|&column| filter.is_selected(column))
+                    .map(|column| CellChange {
+                        header: None,
+                        old_column: old.filter(|r| column < r.len()).map(|_| column),
+                        new_column: new.filter(|r| column < r.len()).map(|_| column),
+                        old: value(old, Some(column)),
+                        new: value(new, Some(column)),
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Writes the changes found by [`diff_csv`] to any `std::io::Write` sink as CSV or JSON.
+///
+/// # Arguments
+///
+/// * `diff` - The [`CsvDiff`] to write.
+/// * `writer` - The sink receiving the formatted changes.
+/// * `format` - The [`DiffFormat`] to write.
+/// * `options` - The [`SearchOptions`] describing how CSV output is written: delimiter, quoting and terminator.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once every change has been written and the sink flushed, or an error if the sink
+/// cannot be written.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the sink cannot be written.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{diff_csv_in, write_diff, DiffFormat, DiffOptions, SearchOptions};
+///
+/// let old = "id,name\n1,Jhon\n";
+/// let new = "id,name\n1,John\n";
+/// let options = SearchOptions::default();
+/// let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+///
+/// let mut output = Vec::new();
+/// write_diff(&diff, &mut output, DiffFormat::Csv, &options).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "change,key,old_record,new_record,column,old_value,new_value\nmodified,1,1,1,name,Jhon,John\n"
+/// );
+/// ```
+pub fn write_diff<W: Write>(
+    diff: &CsvDiff,
+    writer: W,
+    format: DiffFormat,
+    options: &SearchOptions,
+) -> Result<(), CoincidenceError> {
+    match format {
+        DiffFormat::Csv => write_diff_csv(diff, writer, options),
+        DiffFormat::Json => write_diff_json(&diff.changes, writer),
+    }
+}
+
+fn write_diff_csv<W: Write>(diff: &CsvDiff, writer: W, options: &SearchOptions) -> Result<(), CoincidenceError> {
+    let mut wtr = options.writer_builder().from_writer(writer);
+
+    let mut headers = vec!["change".to_string()];
+    match diff.key_columns {
+        1 => headers.push("key".to_string()),
+        key_columns => headers.extend((1..=key_columns).map(|i| format!("key_{}", i))),
+    }
+    headers.extend(["old_record", "new_record", "column", "old_value", "new_value"].map(String::from));
+    wtr.write_record(&headers)?;
+
+    let number = |record: Option<u64>| record.map(|r| r.to_string()).unwrap_or_default();
+    for change in &diff.changes {
+        for cell in &change.cells {
+            let column = match (&cell.header, cell.new_column.or(cell.old_column)) {
+                (Some(header), _) => header.clone(),
+                (None, column) => number(column.map(|c| c as u64)),
+            };
+
+            let mut row = vec![change.kind.as_str().to_string()];
+            row.extend(change.key.iter().cloned());
+            row.extend([
+                number(change.old_record),
+                number(change.new_record),
+                column,
+                cell.old.clone().unwrap_or_default(),
+                cell.new.clone().unwrap_or_default(),
+            ]);
+            wtr.write_record(&row)?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+fn write_diff_json<W: Write>(changes: &[RowChange], mut writer: W) -> Result<(), CoincidenceError> {
+    let number = |value: Option<u64>| value.map_or("null".to_string(), |v| v.to_string());
+    let string = |value: Option<&str>| value.map_or("null".to_string(), json_string);
+
+    write!(writer, "[")?;
+    for (i, change) in changes.iter().enumerate() {
+        let key: Vec<String> = change.key.iter().map(|value| json_string(value)).collect();
+        write!(
+            writer,
+            "{}{{\"change\":\"{}\",\"key\":[{}],\"old_record\":{},\"new_record\":{},\"cells\":[",
+            if i == 0 { "" } else { "," },
+            change.kind.as_str(),
+            key.join(","),
+            number(change.old_record),
+            number(change.new_record),
+        )?;
+
+        for (j, cell) in change.cells.iter().enumerate() {
+            write!(
+                writer,
+                "{}{{\"header\":{},\"old_column\":{},\"new_column\":{},\"old\":{},\"new\":{}}}",
+                if j == 0 { "" } else { "," },
+                string(cell.header.as_deref()),
+                number(cell.old_column.map(|c| c as u64)),
+                number(cell.new_column.map(|c| c as u64)),
+                string(cell.old.as_deref()),
+                string(cell.new.as_deref()),
+            )?;
+        }
+
+        write!(writer, "]}}")?;
+    }
+    writeln!(writer, "]")?;
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+    use csv::Terminator;
+
+    fn summary(diff: &CsvDiff) -> Vec<(ChangeKind, Option<u64>, Option<u64>)> {
+        diff.changes.iter().map(|c| (c.kind, c.old_record, c.new_record)).collect()
+    }
+
+    #[test]
+    fn test_duplicate_keys_pair_in_order() {
+        let old = "id,v\n1,a\n1,b\n";
+        let new = "id,v\n1,a\n1,c\n1,d\n";
+        let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &SearchOptions::default(), &DiffOptions::new());
+
+        assert_eq!(
+            summary(&diff.unwrap()),
+            vec![(ChangeKind::Modified, Some(2), Some(2)), (ChangeKind::Added, None, Some(3))]
+        );
+    }
+
+    #[test]
+    fn test_spilled_diff_finds_the_same_changes() {
+        let old = "id,v\n1,a\n2,b\n3,c\n1,d\n4,e\n5,f\n";
+        let new = "id,v\n5,f\n1,a\n3,x\n6,g\n1,y\n2,b\n";
+        let options = SearchOptions::default();
+        let in_memory = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+        let spilled = DiffOptions::new().spill_partitions(3);
+        let spilled = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &spilled).unwrap();
+
+        assert_eq!(in_memory.changes.len(), 4);
+        assert_eq!(in_memory, spilled);
+    }
+
+    #[test]
+    fn test_column_selection_and_dropped_columns() {
+        let old = "id,name,updated,legacy\n1,Jhon,mon,x\n";
+        let new = "id,name,updated\n1,Jhon,tue\n";
+
+        let options = SearchOptions::new().exclude_column("updated");
+        let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+        let cell = &diff.changes[0].cells[0];
+        assert_eq!(diff.changes[0].cells.len(), 1);
+        assert_eq!((cell.header.as_deref(), cell.old_column, cell.new_column), (Some("legacy"), Some(3), None));
+
+        let options = SearchOptions::new().column("name");
+        let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+        assert!(diff.changes.is_empty());
+    }
+
+    #[test]
+    fn test_headerless_diff_compares_by_index() {
+        let options = SearchOptions::new().has_headers(false).flexible(true);
+        let diff = diff_csv_in("1,a\n".as_bytes(), "1,a,z\n".as_bytes(), [0], &options, &DiffOptions::new()).unwrap();
+
+        assert_eq!(diff.changes[0].cells[0].new_column, Some(2));
+        assert_eq!(diff.changes[0].cells[0].old, None);
+    }
+
+    #[test]
+    fn test_csv_output_follows_the_writer_options() {
+        let old = "id;name\n1;Jhon\n";
+        let new = "id;name\n1;Jhon Doe\n";
+        let options = SearchOptions::new().delimiter(b';');
+        let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+
+        let mut output = Vec::new();
+        write_diff(&diff, &mut output, DiffFormat::Csv, &options.terminator(Terminator::Any(b'|'))).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "change;key;old_record;new_record;column;old_value;new_value|modified;1;1;1;name;Jhon;Jhon Doe|"
+        );
+    }
+
+    #[test]
+    fn test_csv_header_keeps_composite_keys_without_changes() {
+        let data = "country,id,name\nes,1,Jhon\n";
+        let options = SearchOptions::default();
+        let diff = diff_csv_in(data.as_bytes(), data.as_bytes(), 0..2, &options, &DiffOptions::new()).unwrap();
+
+        let mut output = Vec::new();
+        write_diff(&diff, &mut output, DiffFormat::Csv, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "change,key_1,key_2,old_record,new_record,column,old_value,new_value\n"
+        );
+    }
+
+    #[test]
+    fn test_json_output() {
+        let old = "id,name\n1,\"say \"\"hi\"\"\"\n2,x\n";
+        let new = "id,name\n1,bye\n";
+        let options = SearchOptions::default();
+        let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+
+        let mut output = Vec::new();
+        write_diff(&diff, &mut output, DiffFormat::Json, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"[{"change":"modified","key":["1"],"old_record":1,"new_record":1,"cells":["#,
+                r#"{"header":"name","old_column":1,"new_column":1,"old":"say \"hi\"","new":"bye"}]},"#,
+                r#"{"change":"removed","key":["2"],"old_record":2,"new_record":null,"cells":["#,
+                r#"{"header":"id","old_column":0,"new_column":0,"old":"2","new":null},"#,
+                r#"{"header":"name","old_column":1,"new_column":1,"old":"x","new":null}]}]"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_filtered_records_are_not_compared() {
+        let old = "id,status\n1,open\n2,archived\n3,open\n";
+        let new = "id,status\n1,closed\n3,open\n";
+        let options = SearchOptions::new().filter(Query::parse("status != 'archived'").unwrap());
+        let diff = diff_csv_in(old.as_bytes(), new.as_bytes(), ["id"], &options, &DiffOptions::new()).unwrap();
+
+        assert_eq!(summary(&diff), vec![(ChangeKind::Modified, Some(1), Some(1))]);
+    }
+}
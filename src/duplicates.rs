@@ -0,0 +1,709 @@
+use crate::atomic::write_atomically;
+use crate::columns::{resolve_columns, ColumnSelector};
+use crate::cross::key;
+use crate::{compile_pattern, validate_csv_extension, CoincidenceError, SearchOptions};
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// How key values are normalized before records are compared for duplicates.
+#[derive(Debug, Clone, Default)]
+pub enum KeyNormalization {
+    /// Key values are compared exactly.
+    #[default]
+    Exact,
+    /// Key values are lowercased, trimmed and have their inner runs of whitespace collapsed to a single space.
+    Folded,
+    /// Every match of the regular expression in a key value is replaced by the template, which may reference
+    /// capture groups as `$1` or `${name}`.
+    Replace(Regex, String),
+}
+
+impl KeyNormalization {
+    /// Creates a normalization replacing every match of the given regular expression with the template.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if the pattern is invalid.
+    pub fn replace<S: Into<String>>(pattern: &str, template: S) -> Result<Self, CoincidenceError> {
+        Ok(KeyNormalization::Replace(compile_pattern(pattern)?, template.into()))
+    }
+
+    fn apply(&self, key: Vec<String>) -> Vec<String> {
+        match self {
+            KeyNormalization::Exact => key,
+            KeyNormalization::Folded => key
+                .iter()
+                .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+                .collect(),
+            KeyNormalization::Replace(re, template) => key
+                .iter()
+                .map(|value| re.replace_all(value, template.as_str()).into_owned())
+                .collect(),
+        }
+    }
+}
+
+/// Which record of each group of duplicates the dedupe functions keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Keep {
+    /// The first record of each group.
+    #[default]
+    First,
+    /// The last record of each group.
+    Last,
+    /// No record of the group: every duplicated record is dropped.
+    None,
+}
+
+/// Configures how duplicates are detected and which records the dedupe functions keep.
+///
+/// The default compares key values exactly, keeps the first record of each group and indexes the keys in memory.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_duplicates_in, DuplicateOptions, KeyNormalization, SearchOptions};
+///
+/// let data = "name,email\nJhon,JHON@example.com \nMarta,marta@example.com\nJohn,jhon@example.com\n";
+/// let duplicates = DuplicateOptions::new().normalization(KeyNormalization::Folded);
+/// let groups = find_duplicates_in(data.as_bytes(), ["email"], &SearchOptions::default(), &duplicates).unwrap();
+///
+/// assert_eq!(groups[0].records, vec![1, 3]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateOptions {
+    pub(crate) normalization: KeyNormalization,
+    pub(crate) keep: Keep,
+    pub(crate) spill_partitions: usize,
+}
+
+impl DuplicateOptions {
+    /// Creates a new set of duplicate options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how key values are normalized. The default is [`KeyNormalization::Exact`].
+    pub fn normalization(mut self, normalization: KeyNormalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Sets which record of each group the dedupe functions keep. The default is [`Keep::First`].
+    pub fn keep(mut self, keep: Keep) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Spills the keys to the given number of temporary files, partitioned by hash, and indexes one partition at a
+    /// time, so only about `1 / partitions` of the keys are held in memory at once. The dedupe functions also spill
+    /// the numbers of the records they drop, while the find functions still return every group. The default is `0`,
+    /// which indexes every key in memory.
+    pub fn spill_partitions(mut self, partitions: usize) -> Self {
+        self.spill_partitions = partitions;
+        self
+    }
+}
+
+/// A group of records sharing the same (normalized) key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// The normalized key values, one per key column.
+    pub key: Vec<String>,
+    /// The 1-based numbers of the data records in the group, in ascending order. A group always has at least two.
+    pub records: Vec<u64>,
+}
+
+/// Finds the groups of records of the CSV file sharing the same key.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `key_columns` - The columns forming the key.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read and which records are grouped.
+/// * `duplicates` - The [`DuplicateOptions`] describing how keys are normalized and indexed.
+///
+/// # Returns
+///
+/// A `Result` containing the [`DuplicateGroup`] values, ordered by their first record, or an error if there is any
+/// issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records, if
+/// a key column does not exist, or if a spill file cannot be written.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{find_duplicates, DuplicateOptions, SearchOptions};
+///
+/// let duplicates = DuplicateOptions::new().spill_partitions(64);
+/// let groups = find_duplicates("crm.csv", ["first_name", "last_name"], &SearchOptions::default(), &duplicates).unwrap();
+/// ```
+pub fn find_duplicates<K>(
+    file_path: &str,
+    key_columns: K,
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<Vec<DuplicateGroup>, CoincidenceError>
+where
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    find_duplicates_in(file, key_columns, options, duplicates)
+}
+
+/// Finds the groups of records of CSV data read from any `std::io::Read` source sharing the same key.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `key_columns` - The columns forming the key.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read and which records are grouped.
+/// * `duplicates` - The [`DuplicateOptions`] describing how keys are normalized and indexed.
+///
+/// # Returns
+///
+/// A `Result` containing the [`DuplicateGroup`] values, ordered by their first record, or an error if there is any
+/// issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, if a key column does not
+/// exist, or if a spill file cannot be written.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_duplicates_in, DuplicateOptions, KeyNormalization, SearchOptions};
+///
+/// let data = "phone\n555-1234\n(555) 1234\n555-9876\n";
+/// let duplicates = DuplicateOptions::new().normalization(KeyNormalization::replace(r"\D", "").unwrap());
+/// let groups = find_duplicates_in(data.as_bytes(), ["phone"], &SearchOptions::default(), &duplicates).unwrap();
+///
+/// assert_eq!(groups.len(), 1);
+/// assert_eq!(groups[0].key, vec!["5551234"]);
+/// assert_eq!(groups[0].records, vec![1, 2]);
+/// ```
+pub fn find_duplicates_in<R, K>(
+    reader: R,
+    key_columns: K,
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<Vec<DuplicateGroup>, CoincidenceError>
+where
+    R: Read,
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    let key_columns: Vec<ColumnSelector> = key_columns.into_iter().map(Into::into).collect();
+    duplicate_groups(reader, &key_columns, options, duplicates)
+}
+
+/// Writes the records of the CSV file to any `std::io::Write` sink, dropping duplicates as configured by
+/// [`DuplicateOptions::keep`]. The header row, when there is one, is always written.
+///
+/// The file is read twice: once to find the duplicates and once to write the records that are kept.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `writer` - The sink receiving the deduplicated CSV records.
+/// * `key_columns` - The columns forming the key.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read and written, and which records may be
+///   dropped.
+/// * `duplicates` - The [`DuplicateOptions`] describing how keys are normalized and which records are kept.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once every kept record has been written and the sink flushed, or an error if there
+/// is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records, if
+/// the sink or a spill file cannot be written, or if a key column does not exist.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{dedupe_to_writer, DuplicateOptions, Keep, SearchOptions};
+///
+/// let duplicates = DuplicateOptions::new().keep(Keep::Last);
+/// let stdout = std::io::stdout();
+/// dedupe_to_writer("crm.csv", stdout.lock(), ["email"], &SearchOptions::default(), &duplicates).unwrap();
+/// ```
+pub fn dedupe_to_writer<W, K>(
+    file_path: &str,
+    writer: W,
+    key_columns: K,
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<(), CoincidenceError>
+where
+    W: Write,
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(file_path)?;
+
+    let key_columns: Vec<ColumnSelector> = key_columns.into_iter().map(Into::into).collect();
+    let dropped = dropped_records(File::open(file_path)?, &key_columns, options, duplicates)?;
+    write_kept(File::open(file_path)?, writer, dropped, options)
+}
+
+/// Writes the records of the CSV file to another file, dropping duplicates as configured by
+/// [`DuplicateOptions::keep`]. The header row, when there is one, is always written.
+///
+/// `output_path` is replaced atomically, so it may be the input file itself and a failure leaves it untouched.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `output_path` - A string slice representing the file path the deduplicated CSV data is written to.
+/// * `key_columns` - The columns forming the key.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read and written, and which records may be
+///   dropped.
+/// * `duplicates` - The [`DuplicateOptions`] describing how keys are normalized and which records are kept.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once the output file is in place, or an error if there is any issue during
+/// processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the input is not a valid CSV file, cannot be read, contains malformed records, if
+/// the output or a spill file cannot be written, or if a key column does not exist.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{dedupe_to_path, DuplicateOptions, SearchOptions};
+///
+/// dedupe_to_path("crm.csv", "crm_unique.csv", ["email"], &SearchOptions::default(), &DuplicateOptions::default())
+///     .unwrap();
+/// ```
+pub fn dedupe_to_path<K>(
+    file_path: &str,
+    output_path: &str,
+    key_columns: K,
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<(), CoincidenceError>
+where
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(file_path)?;
+
+    let key_columns: Vec<ColumnSelector> = key_columns.into_iter().map(Into::into).collect();
+    let dropped = dropped_records(File::open(file_path)?, &key_columns, options, duplicates)?;
+    let file = File::open(file_path)?;
+    write_atomically(Path::new(output_path), |output| write_kept(file, output, dropped, options))
+}
+
+/// Writes the records of CSV data read from any `std::io::Read` source to any `std::io::Write` sink, dropping
+/// duplicates as configured by [`DuplicateOptions::keep`]. The header row, when there is one, is always written.
+///
+/// Since the data has to be read twice, it is first copied to a temporary file.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `writer` - The sink receiving the deduplicated CSV records.
+/// * `key_columns` - The columns forming the key.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read and written, and which records may be
+///   dropped.
+/// * `duplicates` - The [`DuplicateOptions`] describing how keys are normalized and which records are kept.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once every kept record has been written and the sink flushed, or an error if there
+/// is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, if the sink or a temporary
+/// file cannot be written, or if a key column does not exist.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{dedupe_in, DuplicateOptions, Keep, SearchOptions};
+///
+/// let data = "id,status\n1,new\n2,new\n1,paid\n";
+/// let duplicates = DuplicateOptions::new().keep(Keep::Last);
+/// let mut output = Vec::new();
+/// dedupe_in(data.as_bytes(), &mut output, ["id"], &SearchOptions::default(), &duplicates).unwrap();
+///
+/// assert_eq!(String::from_utf8(output).unwrap(), "id,status\n2,new\n1,paid\n");
+/// ```
+pub fn dedupe_in<R, W, K>(
+    mut reader: R,
+    writer: W,
+    key_columns: K,
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<(), CoincidenceError>
+where
+    R: Read,
+    W: Write,
+    K: IntoIterator,
+    K::Item: Into<ColumnSelector>,
+{
+    let mut spool = tempfile::tempfile()?;
+    io::copy(&mut reader, &mut spool)?;
+    spool.seek(SeekFrom::Start(0))?;
+
+    let key_columns: Vec<ColumnSelector> = key_columns.into_iter().map(Into::into).collect();
+    let dropped = dropped_records(&spool, &key_columns, options, duplicates)?;
+    spool.seek(SeekFrom::Start(0))?;
+    write_kept(&spool, writer, dropped, options)
+}
+
+fn duplicate_groups<R: Read>(
+    reader: R,
+    key_columns: &[ColumnSelector],
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<Vec<DuplicateGroup>, CoincidenceError> {
+    let mut groups = Vec::new();
+    for_each_partition(reader, key_columns, options, duplicates, |partition| {
+        groups.extend(partition);
+        Ok(())
+    })?;
+
+    groups.sort_by_key(|group| group.records[0]);
+    Ok(groups)
+}
+
+/// Finds the records the dedupe functions drop. When spilling, only one partition is grouped at a time and the
+/// dropped record numbers of each partition are spilled too.
+fn dropped_records<R: Read>(
+    reader: R,
+    key_columns: &[ColumnSelector],
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+) -> Result<Dropped, CoincidenceError> {
+    if duplicates.spill_partitions == 0 {
+        let mut dropped = HashSet::new();
+        for_each_partition(reader, key_columns, options, duplicates, |groups| {
+            dropped.extend(dropped_in(&groups, duplicates.keep));
+            Ok(())
+        })?;
+        return Ok(Dropped::InMemory(dropped));
+    }
+
+    let mut partitions = Vec::with_capacity(duplicates.spill_partitions);
+    for_each_partition(reader, key_columns, options, duplicates, |groups| {
+        let mut records: Vec<u64> = dropped_in(&groups, duplicates.keep).collect();
+        records.sort_unstable();
+
+        let mut file = tempfile::tempfile()?;
+        let mut writer = BufWriter::new(&mut file);
+        for record in records {
+            writeln!(writer, "{}", record)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        file.seek(SeekFrom::Start(0))?;
+        partitions.push(SpilledRecords::new(file)?);
+        Ok(())
+    })?;
+
+    Ok(Dropped::Spilled(partitions))
+}
+
+/// Reads the keys and calls `on_groups` with the groups of more than one record: once with every group when keys are
+/// indexed in memory, or once per partition when they are spilled.
+fn for_each_partition<R, F>(
+    reader: R,
+    key_columns: &[ColumnSelector],
+    options: &SearchOptions,
+    duplicates: &DuplicateOptions,
+    mut on_groups: F,
+) -> Result<(), CoincidenceError>
+where
+    R: Read,
+    F: FnMut(Vec<DuplicateGroup>) -> Result<(), CoincidenceError>,
+{
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let headers = options.read_headers(&mut rdr)?;
+    let columns = resolve_columns(key_columns, headers.as_ref())?;
+    let record_filter = options.record_filter(headers.as_ref())?;
+    let keys = rdr
+        .records()
+        .enumerate()
+        .map(|(index, result)| {
+            let record = result?;
+            if !record_filter.is_selected(&record) {
+                return Ok(None);
+            }
+            let key = key(&record, &columns)?;
+            Ok(Some((index as u64 + 1, duplicates.normalization.apply(key))))
+        })
+        .filter_map(Result::transpose);
+
+    match duplicates.spill_partitions {
+        0 => on_groups(group_keys(keys)?),
+        partitions => spill_and_group(keys, partitions, on_groups),
+    }
+}
+
+/// Groups the keys in memory, returning the groups with more than one record.
+fn group_keys<I>(keys: I) -> Result<Vec<DuplicateGroup>, CoincidenceError>
+where
+    I: Iterator<Item = Result<(u64, Vec<String>), CoincidenceError>>,
+{
+    let mut index: HashMap<Vec<String>, Vec<u64>> = HashMap::new();
+    for result in keys {
+        let (record, key) = result?;
+        index.entry(key).or_default().push(record);
+    }
+
+    Ok(index
+        .into_iter()
+        .filter(|(_, records)| records.len() > 1)
+        .map(|(key, records)| DuplicateGroup { key, records })
+        .collect())
+}
+
+/// Writes every key to one of `partitions` temporary files chosen by its hash, so equal keys share a partition, then
+/// groups each partition in memory in turn.
+fn spill_and_group<I, F>(keys: I, partitions: usize, mut on_groups: F) -> Result<(), CoincidenceError>
+where
+    I: Iterator<Item = Result<(u64, Vec<String>), CoincidenceError>>,
+    F: FnMut(Vec<DuplicateGroup>) -> Result<(), CoincidenceError>,
+{
+    let mut writers = (0..partitions)
+        .map(|_| Ok(csv::WriterBuilder::new().flexible(true).from_writer(tempfile::tempfile()?)))
+        .collect::<Result<Vec<_>, CoincidenceError>>()?;
+
+    for result in keys {
+        let (record, key) = result?;
+        let record = record.to_string();
+        let row = std::iter::once(record.as_str()).chain(key.iter().map(String::as_str));
+        writers[partition_of(&key, partitions)].write_record(row)?;
+    }
+
+    for writer in writers {
+        let mut file = writer.into_inner()?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut rdr = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(file);
+        let keys = rdr.records().map(|result| {
+            let row = result?;
+            let record = parse_record_number(row.get(0).unwrap_or_default())?;
+            Ok((record, row.iter().skip(1).map(str::to_string).collect()))
+        });
+        on_groups(group_keys(keys)?)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the records of the groups that are not kept.
+fn dropped_in(groups: &[DuplicateGroup], keep: Keep) -> impl Iterator<Item = u64> + '_ {
+    groups.iter().flat_map(move |group| {
+        let kept = match keep {
+            Keep::First => group.records.first(),
+            Keep::Last => group.records.last(),
+            Keep::None => None,
+        };
+        group.records.iter().copied().filter(move |record| Some(record) != kept)
+    })
+}
+
+/// Returns the spill partition of a key, so equal keys always share a partition.
+pub(crate) fn partition_of(key: &[String], partitions: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % partitions as u64) as usize
+}
+
+/// Parses a record number read back from a spill file.
+pub(crate) fn parse_record_number(text: &str) -> Result<u64, CoincidenceError> {
+    text.parse().map_err(|_| {
+        let message = format!("corrupt spill file: invalid record number `{}`", text);
+        CoincidenceError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+    })
+}
+
+/// The records dropped by the dedupe functions, looked up in ascending record order.
+enum Dropped {
+    InMemory(HashSet<u64>),
+    Spilled(Vec<SpilledRecords>),
+}
+
+impl Dropped {
+    fn contains(&mut self, record: u64) -> Result<bool, CoincidenceError> {
+        match self {
+            Dropped::InMemory(records) => Ok(records.contains(&record)),
+            Dropped::Spilled(partitions) => {
+                for partition in partitions {
+                    if partition.contains(record)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// A sorted list of record numbers spilled to a temporary file, one per line, read back one number at a time.
+struct SpilledRecords {
+    lines: Lines<BufReader<File>>,
+    next: Option<u64>,
+}
+
+impl SpilledRecords {
+    fn new(file: File) -> Result<Self, CoincidenceError> {
+        let mut records = SpilledRecords {
+            lines: BufReader::new(file).lines(),
+            next: None,
+        };
+        records.advance()?;
+        Ok(records)
+    }
+
+    fn advance(&mut self) -> Result<(), CoincidenceError> {
+        self.next = match self.lines.next() {
+            Some(line) => Some(parse_record_number(&line?)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Returns whether the record is in the list. Records must be looked up in ascending order.
+    fn contains(&mut self, record: u64) -> Result<bool, CoincidenceError> {
+        while self.next.is_some_and(|next| next < record) {
+            self.advance()?;
+        }
+        Ok(self.next == Some(record))
+    }
+}
+
+fn write_kept<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    mut dropped: Dropped,
+    options: &SearchOptions,
+) -> Result<(), CoincidenceError> {
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let mut wtr = options.writer_builder().from_writer(writer);
+    if let Some(headers) = options.read_headers(&mut rdr)?.filter(|h| !h.is_empty()) {
+        wtr.write_record(&headers)?;
+    }
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = result?;
+        if !dropped.contains(index as u64 + 1)? {
+            wtr.write_record(&record)?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+
+    const ORDERS: &str = "customer,item\nana,desk\nbob,lamp\nana,chair\ncid,pen\nbob,mug\nana,book\n";
+
+    fn dedupe_orders(keep: Keep) -> String {
+        let mut output = Vec::new();
+        let duplicates = DuplicateOptions::new().keep(keep);
+        dedupe_in(ORDERS.as_bytes(), &mut output, ["customer"], &SearchOptions::default(), &duplicates).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_groups_are_ordered_by_first_record() {
+        let options = SearchOptions::default();
+        let groups = find_duplicates_in(ORDERS.as_bytes(), ["customer"], &options, &DuplicateOptions::new());
+        let groups: Vec<(Vec<String>, Vec<u64>)> = groups.unwrap().into_iter().map(|g| (g.key, g.records)).collect();
+
+        assert_eq!(
+            groups,
+            vec![(vec!["ana".to_string()], vec![1, 3, 6]), (vec!["bob".to_string()], vec![2, 5])]
+        );
+    }
+
+    #[test]
+    fn test_spilling_finds_the_same_groups() {
+        let options = SearchOptions::default();
+        let in_memory = find_duplicates_in(ORDERS.as_bytes(), [0], &options, &DuplicateOptions::new()).unwrap();
+        let spilled = DuplicateOptions::new().spill_partitions(3);
+        let spilled = find_duplicates_in(ORDERS.as_bytes(), [0], &options, &spilled).unwrap();
+
+        assert_eq!(in_memory, spilled);
+    }
+
+    #[test]
+    fn test_spilled_dedupe_keeps_the_same_records() {
+        for keep in [Keep::First, Keep::Last, Keep::None] {
+            let mut output = Vec::new();
+            let duplicates = DuplicateOptions::new().keep(keep).spill_partitions(2);
+            dedupe_in(ORDERS.as_bytes(), &mut output, ["customer"], &SearchOptions::default(), &duplicates).unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), dedupe_orders(keep));
+        }
+    }
+
+    #[test]
+    fn test_corrupt_spill_files_are_reported() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"2\nseven\n").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut records = SpilledRecords::new(file).unwrap();
+
+        assert!(records.contains(2).unwrap());
+        let err = records.contains(3).unwrap_err();
+        assert!(matches!(err, CoincidenceError::Io(err) if err.kind() == io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_keep_modes() {
+        assert_eq!(dedupe_orders(Keep::First), "customer,item\nana,desk\nbob,lamp\ncid,pen\n");
+        assert_eq!(dedupe_orders(Keep::Last), "customer,item\ncid,pen\nbob,mug\nana,book\n");
+        assert_eq!(dedupe_orders(Keep::None), "customer,item\ncid,pen\n");
+    }
+
+    #[test]
+    fn test_dedupe_file_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.csv");
+        std::fs::write(&path, ORDERS).unwrap();
+
+        let path = path.to_str().unwrap();
+        dedupe_to_path(path, path, ["customer"], &SearchOptions::default(), &DuplicateOptions::new()).unwrap();
+
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "customer,item\nana,desk\nbob,lamp\ncid,pen\n");
+    }
+
+    #[test]
+    fn test_filtered_records_are_never_dropped() {
+        let options = SearchOptions::new().filter(Query::parse("item != 'chair'").unwrap());
+        let groups = find_duplicates_in(ORDERS.as_bytes(), ["customer"], &options, &DuplicateOptions::new()).unwrap();
+        assert_eq!(groups[0].records, vec![1, 6]);
+
+        let mut output = Vec::new();
+        dedupe_in(ORDERS.as_bytes(), &mut output, ["customer"], &options, &DuplicateOptions::new()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "customer,item\nana,desk\nbob,lamp\nana,chair\ncid,pen\n");
+    }
+}
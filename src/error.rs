@@ -0,0 +1,170 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::string::FromUtf8Error;
+
+/// The error type returned by every public function of this crate.
+///
+/// Each variant identifies a distinct failure so callers can react to a missing file, a bad
+/// pattern or a malformed CSV row without inspecting error messages. The underlying error, when
+/// there is one, is available through [`Error::source`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CoincidenceError {
+    /// An I/O error occurred while opening, reading or writing data.
+    Io(io::Error),
+    /// The input path does not have a `.csv` extension.
+    InvalidExtension(PathBuf),
+    /// A literal [`Pattern`](crate::Pattern) was built without any terms, so it could never match.
+    EmptyPattern,
+    /// The search pattern is not a valid regular expression.
+    Regex(regex::Error),
+    /// The CSV data could not be parsed or written.
+    Csv {
+        /// The position of the offending record, when the parser reported one.
+        position: Option<csv::Position>,
+        /// The underlying CSV error.
+        source: csv::Error,
+    },
+    /// The generated output is not valid UTF-8.
+    Utf8(FromUtf8Error),
+    /// A column selected by name (or header pattern) does not exist in the header row.
+    UnknownColumn(String),
+    /// A column selected by index is beyond the number of columns in the header row.
+    ColumnOutOfRange {
+        /// The requested zero-based column index.
+        index: usize,
+        /// The number of columns in the header row.
+        len: usize,
+    },
+    /// The two inputs of a join, cross coincidence, linkage or diff select a different number of key columns.
+    KeyMismatch {
+        /// The number of key columns of the left input.
+        left: usize,
+        /// The number of key columns of the right input.
+        right: usize,
+    },
+    /// A query string could not be parsed.
+    Query {
+        /// The zero-based character offset of the offending character in the query.
+        position: usize,
+        /// What is wrong at that position.
+        message: String,
+    },
+}
+
+impl fmt::Display for CoincidenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoincidenceError::Io(err) => write!(f, "I/O error: {}", err),
+            CoincidenceError::InvalidExtension(path) => {
+                write!(f, "The file is not a valid CSV file: {}", path.display())
+            }
+            CoincidenceError::EmptyPattern => write!(f, "The literal pattern has no terms"),
+            CoincidenceError::Regex(err) => write!(f, "Invalid regular expression: {}", err),
+            CoincidenceError::Csv { position: Some(pos), source } => write!(
+                f,
+                "CSV error at record {} (line {}, byte {}): {}",
+                pos.record(),
+                pos.line(),
+                pos.byte(),
+                source
+            ),
+            CoincidenceError::Csv { position: None, source } => write!(f, "CSV error: {}", source),
+            CoincidenceError::Utf8(err) => write!(f, "Invalid UTF-8 output: {}", err),
+            CoincidenceError::UnknownColumn(column) => write!(f, "Unknown column: {}", column),
+            CoincidenceError::ColumnOutOfRange { index, len } => {
+                write!(f, "Column index {} is out of range for {} columns", index, len)
+            }
+            CoincidenceError::KeyMismatch { left, right } => {
+                write!(f, "The left key has {} columns but the right key has {}", left, right)
+            }
+            CoincidenceError::Query { position, message } => {
+                write!(f, "Invalid query at character {}: {}", position, message)
+            }
+        }
+    }
+}
+
+impl Error for CoincidenceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CoincidenceError::Io(err) => Some(err),
+            CoincidenceError::Regex(err) => Some(err),
+            CoincidenceError::Csv { source, .. } => Some(source),
+            CoincidenceError::Utf8(err) => Some(err),
+            CoincidenceError::InvalidExtension(_)
+            | CoincidenceError::EmptyPattern
+            | CoincidenceError::UnknownColumn(_)
+            | CoincidenceError::ColumnOutOfRange { .. }
+            | CoincidenceError::KeyMismatch { .. }
+            | CoincidenceError::Query { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for CoincidenceError {
+    fn from(err: io::Error) -> Self {
+        CoincidenceError::Io(err)
+    }
+}
+
+impl From<regex::Error> for CoincidenceError {
+    fn from(err: regex::Error) -> Self {
+        CoincidenceError::Regex(err)
+    }
+}
+
+impl From<csv::Error> for CoincidenceError {
+    fn from(err: csv::Error) -> Self {
+        if err.is_io_error() {
+            if let csv::ErrorKind::Io(io_err) = err.into_kind() {
+                return CoincidenceError::Io(io_err);
+            }
+            unreachable!("is_io_error implies an I/O error kind");
+        }
+
+        CoincidenceError::Csv {
+            position: err.position().cloned(),
+            source: err,
+        }
+    }
+}
+
+impl<W> From<csv::IntoInnerError<W>> for CoincidenceError {
+    fn from(err: csv::IntoInnerError<W>) -> Self {
+        CoincidenceError::Io(err.into_error())
+    }
+}
+
+impl From<FromUtf8Error> for CoincidenceError {
+    fn from(err: FromUtf8Error) -> Self {
+        CoincidenceError::Utf8(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_error_keeps_position() {
+        let data = "a,b\n1,2\n3\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        let err = rdr.records().find_map(|r| r.err()).unwrap();
+
+        match CoincidenceError::from(err) {
+            CoincidenceError::Csv { position: Some(pos), .. } => assert_eq!(pos.line(), 3),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_source_chaining() {
+        let pattern = String::from("(");
+        let err = CoincidenceError::from(regex::Regex::new(&pattern).unwrap_err());
+        assert!(err.source().is_some());
+        assert!(CoincidenceError::EmptyPattern.source().is_none());
+    }
+}
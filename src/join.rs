@@ -0,0 +1,543 @@
+use crate::atomic::write_atomically;
+use crate::columns::{resolve_columns, ColumnSelector};
+use crate::cross::key;
+use crate::{compile_pattern, validate_csv_extension, CoincidenceError, SearchOptions};
+use csv::StringRecord;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Which records the join functions write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JoinType {
+    /// Only the pairs of left and right records sharing a key.
+    #[default]
+    Inner,
+    /// Every left record, joined with the right records sharing its key or with empty right columns.
+    Left,
+    /// Every right record, joined with the left records sharing its key or with empty left columns.
+    Right,
+    /// Every record of both sides, joined where the keys match.
+    Full,
+}
+
+/// Configures how the join functions match keys and name the output columns.
+///
+/// The default is an inner join on exact key values, suffixing clashing column names with `_left` and `_right`.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{join_in, JoinOptions, JoinType, SearchOptions};
+///
+/// let customers = "id,name\nC-001,Jhon\nC-002,Marta\n";
+/// let orders = "customer,name\n1,Desk\n1,Lamp\n3,Chair\n";
+///
+/// let join = JoinOptions::new()
+///     .join_type(JoinType::Left)
+///     .left_key_pattern(r"^C-0*(\d+)$")
+///     .unwrap();
+/// let options = SearchOptions::default();
+/// let mut output = Vec::new();
+/// join_in(customers.as_bytes(), orders.as_bytes(), &mut output, ["id"], ["customer"], &options, &join).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "id,name_left,name_right\nC-001,Jhon,Desk\nC-001,Jhon,Lamp\nC-002,Marta,\n"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct JoinOptions {
+    pub(crate) join_type: JoinType,
+    pub(crate) left_suffix: String,
+    pub(crate) right_suffix: String,
+    pub(crate) left_key_pattern: Option<Regex>,
+    pub(crate) right_key_pattern: Option<Regex>,
+}
+
+impl Default for JoinOptions {
+    fn default() -> Self {
+        JoinOptions {
+            join_type: JoinType::Inner,
+            left_suffix: "_left".to_string(),
+            right_suffix: "_right".to_string(),
+            left_key_pattern: None,
+            right_key_pattern: None,
+        }
+    }
+}
+
+impl JoinOptions {
+    /// Creates a new set of join options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets which records are written. The default is [`JoinType::Inner`].
+    pub fn join_type(mut self, join_type: JoinType) -> Self {
+        self.join_type = join_type;
+        self
+    }
+
+    /// Sets the suffixes appended to the names of the columns present on both sides. The defaults are `"_left"` and
+    /// `"_right"`.
+    pub fn suffixes<L: Into<String>, R: Into<String>>(mut self, left: L, right: R) -> Self {
+        self.left_suffix = left.into();
+        self.right_suffix = right.into();
+        self
+    }
+
+    /// Normalizes the key values of both sides with the given regular expression before comparing them.
+    ///
+    /// Each key value is replaced by the text of the first capture group of the pattern, or by the whole match when
+    /// the pattern has no groups. Records whose key values do not match the pattern never join.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if the pattern is invalid.
+    pub fn key_pattern(self, pattern: &str) -> Result<Self, CoincidenceError> {
+        self.left_key_pattern(pattern)?.right_key_pattern(pattern)
+    }
+
+    /// Normalizes the key values of the left side only, as described in [`JoinOptions::key_pattern`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if the pattern is invalid.
+    pub fn left_key_pattern(mut self, pattern: &str) -> Result<Self, CoincidenceError> {
+        self.left_key_pattern = Some(compile_pattern(pattern)?);
+        Ok(self)
+    }
+
+    /// Normalizes the key values of the right side only, as described in [`JoinOptions::key_pattern`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if the pattern is invalid.
+    pub fn right_key_pattern(mut self, pattern: &str) -> Result<Self, CoincidenceError> {
+        self.right_key_pattern = Some(compile_pattern(pattern)?);
+        Ok(self)
+    }
+}
+
+/// Joins two CSV files on matching key columns and streams the joined records to any `std::io::Write` sink.
+///
+/// Each output record holds every column of the left record followed by the non-key columns of the right record.
+/// The right file is loaded into memory and indexed by key, so the smaller file should be passed as `right_path`.
+///
+/// # Arguments
+///
+/// * `left_path` - A string slice representing the file path to the left CSV file.
+/// * `right_path` - A string slice representing the file path to the right CSV file.
+/// * `writer` - The sink receiving the joined CSV records.
+/// * `left_keys` - The key columns of the left file.
+/// * `right_keys` - The key columns of the right file, in the same order as `left_keys`.
+/// * `options` - The [`SearchOptions`] describing how both files are read, which of their records are joined and how
+///   the joined records are written.
+/// * `join` - The [`JoinOptions`] describing the join type, key normalization and column naming.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once every joined record has been written and the sink flushed, or an error if
+/// there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either file is not a valid CSV file, cannot be read, contains malformed records,
+/// if the sink cannot be written, if a key column does not exist or if the two sides select a different number of
+/// key columns.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{join_to_writer, JoinOptions, JoinType, SearchOptions};
+///
+/// let join = JoinOptions::new().join_type(JoinType::Full);
+/// let options = SearchOptions::default();
+/// let stdout = std::io::stdout();
+/// join_to_writer("orders.csv", "customers.csv", stdout.lock(), ["customer_id"], ["id"], &options, &join).unwrap();
+/// ```
+pub fn join_to_writer<W, KL, KR>(
+    left_path: &str,
+    right_path: &str,
+    writer: W,
+    left_keys: KL,
+    right_keys: KR,
+    options: &SearchOptions,
+    join: &JoinOptions,
+) -> Result<(), CoincidenceError>
+where
+    W: Write,
+    KL: IntoIterator,
+    KL::Item: Into<ColumnSelector>,
+    KR: IntoIterator,
+    KR::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(left_path)?;
+    validate_csv_extension(right_path)?;
+
+    let left = File::open(left_path)?;
+    let right = File::open(right_path)?;
+    join_in(left, right, writer, left_keys, right_keys, options, join)
+}
+
+/// Joins two CSV files on matching key columns and writes the joined records to another file.
+///
+/// `output_path` is replaced atomically, so it may be the input file itself and a failure leaves it untouched.
+///
+/// # Arguments
+///
+/// * `left_path` - A string slice representing the file path to the left CSV file.
+/// * `right_path` - A string slice representing the file path to the right CSV file.
+/// * `output_path` - A string slice representing the file path the joined CSV data is written to.
+/// * `left_keys` - The key columns of the left file.
+/// * `right_keys` - The key columns of the right file, in the same order as `left_keys`.
+/// * `options` - The [`SearchOptions`] describing how both files are read, which of their records are joined and how
+///   the joined records are written.
+/// * `join` - The [`JoinOptions`] describing the join type, key normalization and column naming.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once the output file is in place, or an error if there is any issue during
+/// processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either input is not a valid CSV file, cannot be read, contains malformed records,
+/// if the output cannot be written, if a key column does not exist or if the two sides select a different number of
+/// key columns.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{join_to_path, JoinOptions, SearchOptions};
+///
+/// let join = JoinOptions::new().suffixes("_order", "_customer");
+/// join_to_path(
+///     "orders.csv",
+///     "customers.csv",
+///     "orders_with_customers.csv",
+///     ["customer_id", "region"],
+///     ["id", "region"],
+///     &SearchOptions::default(),
+///     &join,
+/// )
+/// .unwrap();
+/// ```
+pub fn join_to_path<KL, KR>(
+    left_path: &str,
+    right_path: &str,
+    output_path: &str,
+    left_keys: KL,
+    right_keys: KR,
+    options: &SearchOptions,
+    join: &JoinOptions,
+) -> Result<(), CoincidenceError>
+where
+    KL: IntoIterator,
+    KL::Item: Into<ColumnSelector>,
+    KR: IntoIterator,
+    KR::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(left_path)?;
+    validate_csv_extension(right_path)?;
+
+    let left = File::open(left_path)?;
+    let right = File::open(right_path)?;
+    write_atomically(Path::new(output_path), |output| {
+        join_in(left, right, output, left_keys, right_keys, options, join)
+    })
+}
+
+/// Joins CSV data read from two `std::io::Read` sources on matching key columns and streams the joined records to
+/// any `std::io::Write` sink.
+///
+/// Each output record holds every column of the left record followed by the non-key columns of the right record.
+/// Right-only records of [`JoinType::Right`] and [`JoinType::Full`] joins are written after the left records, with
+/// the left key columns filled from the right key. When both inputs have a header row, a joined header row is
+/// written first, with clashing column names suffixed as configured in [`JoinOptions::suffixes`].
+///
+/// # Arguments
+///
+/// * `left` - The source of the left CSV data.
+/// * `right` - The source of the right CSV data, which is loaded into memory and indexed by key.
+/// * `writer` - The sink receiving the joined CSV records.
+/// * `left_keys` - The key columns of the left data.
+/// * `right_keys` - The key columns of the right data, in the same order as `left_keys`.
+/// * `options` - The [`SearchOptions`] describing how both inputs are read, which of their records are joined and how
+///   the joined records are written.
+/// * `join` - The [`JoinOptions`] describing the join type, key normalization and column naming.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once every joined record has been written and the sink flushed, or an error if
+/// there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either input cannot be read, contains malformed records, if the sink cannot be
+/// written, if a key column does not exist or if the two sides select a different number of key columns.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{join_in, JoinOptions, JoinType, SearchOptions};
+///
+/// let left = "id,city\n1,Madrid\n2,Paris\n";
+/// let right = "id,country\n2,France\n3,Italy\n";
+/// let join = JoinOptions::new().join_type(JoinType::Full);
+/// let mut output = Vec::new();
+///
+/// join_in(left.as_bytes(), right.as_bytes(), &mut output, ["id"], ["id"], &SearchOptions::default(), &join).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "id,city,country\n1,Madrid,\n2,Paris,France\n3,,Italy\n"
+/// );
+/// ```
+pub fn join_in<L, R, W, KL, KR>(
+    left: L,
+    right: R,
+    writer: W,
+    left_keys: KL,
+    right_keys: KR,
+    options: &SearchOptions,
+    join: &JoinOptions,
+) -> Result<(), CoincidenceError>
+where
+    L: Read,
+    R: Read,
+    W: Write,
+    KL: IntoIterator,
+    KL::Item: Into<ColumnSelector>,
+    KR: IntoIterator,
+    KR::Item: Into<ColumnSelector>,
+{
+    let left_keys: Vec<ColumnSelector> = left_keys.into_iter().map(Into::into).collect();
+    let right_keys: Vec<ColumnSelector> = right_keys.into_iter().map(Into::into).collect();
+
+    let mut left_rdr = options.reader_builder().from_reader(left);
+    let mut right_rdr = options.reader_builder().from_reader(right);
+    let mut wtr = options.writer_builder().from_writer(writer);
+    let left_headers = options.read_headers(&mut left_rdr)?;
+    let right_headers = options.read_headers(&mut right_rdr)?;
+    let left_columns = resolve_columns(&left_keys, left_headers.as_ref())?;
+    let right_columns = resolve_columns(&right_keys, right_headers.as_ref())?;
+    let left_filter = options.record_filter(left_headers.as_ref())?;
+    let right_filter = options.record_filter(right_headers.as_ref())?;
+
+    if left_columns.len() != right_columns.len() {
+        return Err(CoincidenceError::KeyMismatch {
+            left: left_columns.len(),
+            right: right_columns.len(),
+        });
+    }
+
+    let mut right_records = Vec::new();
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for result in right_rdr.records() {
+        let record = result?;
+        if !right_filter.is_selected(&record) {
+            continue;
+        }
+        if let Some(key) = normalize(join.right_key_pattern.as_ref(), key(&record, &right_columns)?) {
+            index.entry(key).or_default().push(right_records.len());
+        }
+        right_records.push(record);
+    }
+
+    let right_width = right_headers
+        .as_ref()
+        .or(right_records.first())
+        .map_or(0, StringRecord::len);
+    let right_kept: Vec<usize> = (0..right_width).filter(|c| !right_columns.contains(c)).collect();
+
+    if let (Some(left_headers), Some(right_headers)) = (&left_headers, &right_headers) {
+        if !left_headers.is_empty() || !right_headers.is_empty() {
+            wtr.write_record(joined_headers(left_headers, right_headers, &right_kept, join))?;
+        }
+    }
+
+    let keeps_left = matches!(join.join_type, JoinType::Left | JoinType::Full);
+    let keeps_right = matches!(join.join_type, JoinType::Right | JoinType::Full);
+    let mut right_joined = vec![false; right_records.len()];
+    let mut left_width = left_headers.as_ref().map(StringRecord::len);
+    let mut output = StringRecord::new();
+
+    for result in left_rdr.records() {
+        let record = result?;
+        if !left_filter.is_selected(&record) {
+            continue;
+        }
+        left_width.get_or_insert(record.len());
+
+        let joined = normalize(join.left_key_pattern.as_ref(), key(&record, &left_columns)?)
+            .and_then(|key| index.get(&key))
+            .map_or(&[][..], Vec::as_slice);
+
+        for &right in joined {
+            right_joined[right] = true;
+            output.clear();
+            output.extend(record.iter());
+            output.extend(right_kept.iter().map(|&c| right_records[right].get(c).unwrap_or("")));
+            wtr.write_record(&output)?;
+        }
+
+        if joined.is_empty() && keeps_left {
+            output.clear();
+            output.extend(record.iter());
+            output.extend(right_kept.iter().map(|_| ""));
+            wtr.write_record(&output)?;
+        }
+    }
+
+    if keeps_right {
+        let key_width = left_columns.iter().max().map_or(0, |&c| c + 1);
+        let mut left_part = vec![""; left_width.unwrap_or(0).max(key_width)];
+
+        for (record, _) in right_records.iter().zip(&right_joined).filter(|(_, &joined)| !joined) {
+            left_part.iter_mut().for_each(|field| *field = "");
+            for (&left_column, &right_column) in left_columns.iter().zip(&right_columns) {
+                left_part[left_column] = record.get(right_column).unwrap_or("");
+            }
+
+            output.clear();
+            output.extend(left_part.iter());
+            output.extend(right_kept.iter().map(|&c| record.get(c).unwrap_or("")));
+            wtr.write_record(&output)?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Applies the key pattern to every key value, returning `None` when a value does not match it.
+fn normalize(pattern: Option<&Regex>, key: Vec<String>) -> Option<Vec<String>> {
+    let Some(re) = pattern else {
+        return Some(key);
+    };
+
+    key.iter()
+        .map(|value| {
+            let caps = re.captures(value)?;
+            let group = if re.captures_len() > 1 { caps.get(1) } else { caps.get(0) };
+            group.map(|m| m.as_str().to_string())
+        })
+        .collect()
+}
+
+/// Builds the joined header row, suffixing the names present on both sides.
+fn joined_headers(
+    left: &StringRecord,
+    right: &StringRecord,
+    right_kept: &[usize],
+    join: &JoinOptions,
+) -> Vec<String> {
+    let right_names: Vec<&str> = right_kept.iter().map(|&c| right.get(c).unwrap_or("")).collect();
+    let left_names: HashSet<&str> = left.iter().collect();
+    let clashing: HashSet<&str> = right_names.iter().copied().filter(|name| left_names.contains(name)).collect();
+
+    let left_headers = left.iter().map(|name| match clashing.contains(name) {
+        true => format!("{}{}", name, join.left_suffix),
+        false => name.to_string(),
+    });
+    let right_headers = right_names.iter().map(|&name| match clashing.contains(name) {
+        true => format!("{}{}", name, join.right_suffix),
+        false => name.to_string(),
+    });
+
+    left_headers.chain(right_headers).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+
+    const CUSTOMERS: &str = "id,name\n1,Jhon\n2,Marta\n";
+    const PURCHASES: &str = "name,id\nDesk,1\nLamp,1\nChair,3\n";
+
+    fn join_purchases(join_type: JoinType) -> String {
+        let join = JoinOptions::new().join_type(join_type).suffixes("_l", "_r");
+        let options = SearchOptions::default();
+        let mut output = Vec::new();
+        join_in(CUSTOMERS.as_bytes(), PURCHASES.as_bytes(), &mut output, ["id"], ["id"], &options, &join).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_join_types() {
+        assert_eq!(join_purchases(JoinType::Inner), "id,name_l,name_r\n1,Jhon,Desk\n1,Jhon,Lamp\n");
+        assert_eq!(join_purchases(JoinType::Left), "id,name_l,name_r\n1,Jhon,Desk\n1,Jhon,Lamp\n2,Marta,\n");
+        assert_eq!(join_purchases(JoinType::Right), "id,name_l,name_r\n1,Jhon,Desk\n1,Jhon,Lamp\n3,,Chair\n");
+        assert_eq!(
+            join_purchases(JoinType::Full),
+            "id,name_l,name_r\n1,Jhon,Desk\n1,Jhon,Lamp\n2,Marta,\n3,,Chair\n"
+        );
+    }
+
+    #[test]
+    fn test_multi_column_keys_without_headers() {
+        let left = "es,1,Madrid\nfr,1,Paris\n";
+        let right = "1,es,3.2M\n";
+        let options = SearchOptions::new().has_headers(false);
+        let mut output = Vec::new();
+        join_in(left.as_bytes(), right.as_bytes(), &mut output, [0, 1], [1, 0], &options, &JoinOptions::new())
+            .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "es,1,Madrid,3.2M\n");
+    }
+
+    #[test]
+    fn test_key_pattern_rejects_unmatched_values() {
+        let left = "code\nID-7\nbad\n";
+        let right = "code\n7\n";
+        let join = JoinOptions::new().key_pattern(r"(\d+)$").unwrap().join_type(JoinType::Full);
+        let mut output = Vec::new();
+        join_in(left.as_bytes(), right.as_bytes(), &mut output, ["code"], ["code"], &SearchOptions::default(), &join)
+            .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "code\nID-7\nbad\n");
+    }
+
+    #[test]
+    fn test_key_count_mismatch() {
+        let mut output = Vec::new();
+        let result = join_in(
+            CUSTOMERS.as_bytes(),
+            PURCHASES.as_bytes(),
+            &mut output,
+            ["id", "name"],
+            ["id"],
+            &SearchOptions::default(),
+            &JoinOptions::new(),
+        );
+
+        assert!(matches!(result, Err(CoincidenceError::KeyMismatch { left: 2, right: 1 })));
+    }
+
+    #[test]
+    fn test_filtered_records_are_not_joined() {
+        let options = SearchOptions::new().filter(Query::parse("name != 'Lamp'").unwrap());
+        let join = JoinOptions::new().join_type(JoinType::Full).suffixes("_l", "_r");
+        let mut output = Vec::new();
+        join_in(CUSTOMERS.as_bytes(), PURCHASES.as_bytes(), &mut output, ["id"], ["id"], &options, &join).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "id,name_l,name_r\n1,Jhon,Desk\n2,Marta,\n3,,Chair\n");
+    }
+
+    #[test]
+    fn test_right_join_when_every_left_record_is_filtered_out() {
+        let options = SearchOptions::new()
+            .has_headers(false)
+            .filter(Query::parse("0 != \"x\"").unwrap());
+        let join = JoinOptions::new().join_type(JoinType::Right);
+        let mut output = Vec::new();
+        join_in("x\n".as_bytes(), "a,b,k\n".as_bytes(), &mut output, [2], [2], &options, &join).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), ",,k,a,b\n");
+    }
+}
@@ -1,7 +1,56 @@
-use csv::{Reader, ReaderBuilder, WriterBuilder};
+mod atomic;
+mod columns;
+mod context;
+mod count;
+mod cross;
+mod diff;
+mod duplicates;
+mod error;
+mod join;
+mod json;
+mod linkage;
+mod matching;
+mod merge;
+mod multi;
+mod options;
+mod pattern;
+mod query;
+mod rules;
+mod search;
+
+pub use columns::ColumnSelector;
+pub use context::{find_records, find_records_in, ContextOptions, ContextRecord, RecordMatches};
+pub use count::{
+    count_report, count_report_in, count_report_many, count_report_many_in, ColumnCount, CountMode, CountReport,
+    ValueCount,
+};
+pub use csv::{Terminator, Trim};
+pub use cross::{find_cross_coincidences, find_cross_coincidences_in, CrossMatch, CrossMode};
+pub use diff::{
+    diff_csv, diff_csv_in, write_diff, CellChange, ChangeKind, CsvDiff, DiffFormat, DiffOptions, RowChange,
+};
+pub use duplicates::{
+    dedupe_in, dedupe_to_path, dedupe_to_writer, find_duplicates, find_duplicates_in, DuplicateGroup, DuplicateOptions,
+    Keep, KeyNormalization,
+};
+pub use error::CoincidenceError;
+pub use join::{join_in, join_to_path, join_to_writer, JoinOptions, JoinType};
+pub use linkage::{link_records, link_records_in, LinkOptions, LinkPair, Similarity};
+pub use matching::Match;
+pub use merge::{
+    merge_coincidence_in_place, merge_coincidence_to_path, merge_coincidence_to_writer, MergeMode, MergeOptions,
+    ReplaceScope, Replacement,
+};
+pub use multi::{count_many, count_many_in, find_many, find_many_in, MultiMatch, PatternCount, PatternSet};
+pub use options::SearchOptions;
+pub use pattern::{MatchMode, Pattern};
+pub use query::{query_records, query_records_in, Comparison, Expr, Predicate, Query, Value};
+pub use rules::{find_rule_matches, find_rule_matches_in, ColumnRules, RuleMatch, RuleMode};
+pub use search::{matches, matches_in, Matches};
+
 use regex::Regex;
-use std::error::Error;
 use std::fs::File;
+use std::io::{Read, Write};
 use std::path::Path;
 
 /// Finds partial matches in the CSV file based on the given regular expression pattern.
@@ -9,7 +58,7 @@ use std::path::Path;
 /// # Arguments
 ///
 /// * `file_path` - A string slice representing the file path to the input CSV file.
-/// * `regex_pattern` - A string slice representing the regular expression pattern to match against the CSV records.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
 ///
 /// # Returns
 ///
@@ -17,11 +66,12 @@ use std::path::Path;
 ///
 /// # Errors
 ///
-/// Returns an error if the file is not a valid CSV file or if there is an issue with the regular expression pattern.
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
 ///
 /// # Example
 ///
-/// ```
+/// ```no_run
 /// use std::error::Error;
 /// use csv_coincidence::find_partial_matches;
 ///
@@ -38,25 +88,189 @@ use std::path::Path;
 ///     }
 /// }
 /// ```
-pub fn find_partial_matches(file_path: &str, regex_pattern: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    validate_csv_extension(file_path);
+pub fn find_partial_matches<P: Into<Pattern>>(
+    file_path: &str,
+    regex_pattern: P,
+) -> Result<Vec<String>, CoincidenceError> {
+    find_partial_matches_with(file_path, regex_pattern, &SearchOptions::default())
+}
+
+/// Finds partial matches in the CSV file based on the given regular expression pattern, using the given reader options.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing a vector of strings with the partial matches if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{find_partial_matches_with, SearchOptions};
+///
+/// let options = SearchOptions::new().delimiter(b';');
+/// let matches = find_partial_matches_with("example.csv", r"\bpartial\b", &options).unwrap();
+/// ```
+pub fn find_partial_matches_with<P: Into<Pattern>>(
+    file_path: &str,
+    regex_pattern: P,
+    options: &SearchOptions,
+) -> Result<Vec<String>, CoincidenceError> {
+    validate_csv_extension(file_path)?;
 
     let file = File::open(file_path)?;
-    let mut rdr = Reader::from_reader(file);
-
-    let re = Regex::new(regex_pattern)?;
-    let mut partial_matches = Vec::new();
-
-    for result in rdr.records() {
-        let record = result?;
-        for field in record.iter() {
-            if re.is_match(field) {
-                partial_matches.push(field.to_string());
-            }
-        }
-    }
+    find_partial_matches_in(file, regex_pattern, options)
+}
+
+/// Finds partial matches in CSV data read from any `std::io::Read` source.
+///
+/// This is the reader-based counterpart of [`find_partial_matches`], useful for searching stdin, in-memory buffers
+/// or decompressed streams.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing a vector of strings with the partial matches if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, or if the regular
+/// expression pattern is invalid.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_partial_matches_in, SearchOptions};
+///
+/// let data = "name,city\nJhon,madrid\nmarta,Paris\n";
+/// let matches = find_partial_matches_in(data.as_bytes(), r"^[A-Z]", &SearchOptions::default()).unwrap();
+///
+/// assert_eq!(matches, vec!["Jhon", "Paris"]);
+/// ```
+pub fn find_partial_matches_in<R: Read, P: Into<Pattern>>(
+    reader: R,
+    regex_pattern: P,
+    options: &SearchOptions,
+) -> Result<Vec<String>, CoincidenceError> {
+    let matches = find_matches_in(reader, regex_pattern, options)?;
+    Ok(matches.into_iter().map(|m| m.value).collect())
+}
+
+/// Finds the matches in the CSV file based on the given regular expression pattern, reporting where each one was found.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+///
+/// # Returns
+///
+/// A `Result` containing a vector of [`Match`] values, one per matching field, if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::find_matches;
+///
+/// for m in find_matches("example.csv", r"\d{4}-\d{4}").unwrap() {
+///     println!("record {} column {:?}: {}", m.record, m.header, m.as_str());
+/// }
+/// ```
+pub fn find_matches<P: Into<Pattern>>(file_path: &str, regex_pattern: P) -> Result<Vec<Match>, CoincidenceError> {
+    find_matches_with(file_path, regex_pattern, &SearchOptions::default())
+}
 
-    Ok(partial_matches)
+/// Finds the matches in the CSV file based on the given regular expression pattern, using the given reader options.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing a vector of [`Match`] values, one per matching field, if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{find_matches_with, SearchOptions};
+///
+/// let options = SearchOptions::new().delimiter(b';');
+/// let matches = find_matches_with("example.csv", r"\d{4}", &options).unwrap();
+/// ```
+pub fn find_matches_with<P: Into<Pattern>>(
+    file_path: &str,
+    regex_pattern: P,
+    options: &SearchOptions,
+) -> Result<Vec<Match>, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    find_matches_in(file, regex_pattern, options)
+}
+
+/// Finds the matches in CSV data read from any `std::io::Read` source, reporting where each one was found.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing a vector of [`Match`] values, one per matching field, if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, or if the regular
+/// expression pattern is invalid.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_matches_in, SearchOptions};
+///
+/// let data = "name,phone\nJhon,555-1234\n";
+/// let matches = find_matches_in(data.as_bytes(), r"\d{4}", &SearchOptions::default()).unwrap();
+///
+/// assert_eq!(matches[0].record, 1);
+/// assert_eq!(matches[0].line, 2);
+/// assert_eq!(matches[0].header.as_deref(), Some("phone"));
+/// assert_eq!(matches[0].as_str(), "1234");
+/// ```
+pub fn find_matches_in<R: Read, P: Into<Pattern>>(
+    reader: R,
+    regex_pattern: P,
+    options: &SearchOptions,
+) -> Result<Vec<Match>, CoincidenceError> {
+    matches_in(reader, regex_pattern, options)?.collect()
 }
 
 /// Counts the number of occurrences of a specific pattern in the CSV file.
@@ -64,7 +278,7 @@ pub fn find_partial_matches(file_path: &str, regex_pattern: &str) -> Result<Vec<
 /// # Arguments
 ///
 /// * `file_path` - A string slice representing the file path to the input CSV file.
-/// * `pattern` - A string slice representing the regular expression pattern to match against the CSV records.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
 ///
 /// # Returns
 ///
@@ -72,11 +286,12 @@ pub fn find_partial_matches(file_path: &str, regex_pattern: &str) -> Result<Vec<
 ///
 /// # Errors
 ///
-/// Returns an error if the file is not a valid CSV file or if there is an issue with the regular expression pattern.
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
 ///
 /// # Example
 ///
-/// ```
+/// ```no_run
 /// use std::error::Error;
 /// use csv_coincidence::count_coincidences;
 ///
@@ -93,25 +308,82 @@ pub fn find_partial_matches(file_path: &str, regex_pattern: &str) -> Result<Vec<
 ///     }
 /// }
 /// ```
-pub fn count_coincidences(file_path: &str, patron: &str) -> Result<usize, Box<dyn Error>> {
-    validate_csv_extension(file_path);
-
-    let re = Regex::new(patron)?;
+pub fn count_coincidences<P: Into<Pattern>>(file_path: &str, patron: P) -> Result<usize, CoincidenceError> {
+    count_coincidences_with(file_path, patron, &SearchOptions::default())
+}
 
+/// Counts the number of occurrences of a specific pattern in the CSV file, using the given reader options.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `patron` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing the count selected by the options' [`CountMode`] (matching fields by default) if
+/// successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{count_coincidences_with, SearchOptions};
+///
+/// let options = SearchOptions::new().delimiter(b';');
+/// let count = count_coincidences_with("example.csv", r"\bexample\b", &options).unwrap();
+/// ```
+pub fn count_coincidences_with<P: Into<Pattern>>(
+    file_path: &str,
+    patron: P,
+    options: &SearchOptions,
+) -> Result<usize, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let matcher = options.matcher(&patron.into())?;
     let file = File::open(file_path)?;
-    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
-
-    let mut contador = 0;
-    for result in rdr.records() {
-        let record = result?;
-        for field in record.iter() {
-            if re.is_match(field) {
-                contador += 1;
-            }
-        }
-    }
+    Ok(count::count_matches(file, &matcher, options)?.get(options.count_mode))
+}
 
-    Ok(contador)
+/// Counts the number of occurrences of a specific pattern in CSV data read from any `std::io::Read` source.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing the count selected by the options' [`CountMode`] (matching fields by default) if
+/// successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, or if the regular
+/// expression pattern is invalid.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_coincidences_in, SearchOptions};
+///
+/// let data = "name,city\nJhon,madrid\nmarta,Paris\n";
+///
+/// assert_eq!(count_coincidences_in(data.as_bytes(), r"^[A-Z]", &SearchOptions::default()).unwrap(), 2);
+/// ```
+pub fn count_coincidences_in<R: Read, P: Into<Pattern>>(
+    reader: R,
+    patron: P,
+    options: &SearchOptions,
+) -> Result<usize, CoincidenceError> {
+    let matcher = options.matcher(&patron.into())?;
+    Ok(count::count_matches(reader, &matcher, options)?.get(options.count_mode))
 }
 
 /// Merges the records in a CSV file that match a specific pattern and replaces those matches with "[MERGED]".
@@ -119,7 +391,7 @@ pub fn count_coincidences(file_path: &str, patron: &str) -> Result<usize, Box<dy
 /// # Arguments
 ///
 /// * `file_path` - A string slice representing the file path to the input CSV file.
-/// * `pattern` - A string slice representing the regular expression pattern to match against the CSV records.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
 ///
 /// # Returns
 ///
@@ -127,11 +399,12 @@ pub fn count_coincidences(file_path: &str, patron: &str) -> Result<usize, Box<dy
 ///
 /// # Errors
 ///
-/// Returns an error if the file is not a valid CSV file or if there is an issue with the regular expression pattern.
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
 ///
 /// # Example
 ///
-/// ```
+/// ```no_run
 /// use std::error::Error;
 /// use csv_coincidence::merge_coincidence;
 ///
@@ -148,39 +421,100 @@ pub fn count_coincidences(file_path: &str, patron: &str) -> Result<usize, Box<dy
 ///     }
 /// }
 /// ```
-pub fn merge_coincidence(file_path: &str, patron: &str) -> Result<String, Box<dyn Error>> {
-    validate_csv_extension(file_path);
+pub fn merge_coincidence<P: Into<Pattern>>(file_path: &str, patron: P) -> Result<String, CoincidenceError> {
+    merge_coincidence_with(file_path, patron, &SearchOptions::default(), &MergeOptions::default())
+}
 
-    let re = Regex::new(patron)?;
+/// Merges the records in a CSV file that match a specific pattern and replaces those matches as configured by the
+/// given [`MergeOptions`]. The merged records are written with the same delimiter, quote and terminator.
+///
+/// The whole output is kept in memory; use [`merge_coincidence_to_writer`] or [`merge_coincidence_to_path`] for large
+/// files.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `patron` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+/// * `merge` - The [`MergeOptions`] describing how the matches are replaced.
+///
+/// # Returns
+///
+/// A `Result` containing a `String` with the merged CSV data if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{merge_coincidence_with, MergeOptions, Replacement, SearchOptions};
+///
+/// let options = SearchOptions::new().delimiter(b';');
+/// let merge = MergeOptions::new().replacement(Replacement::fixed("***"));
+/// let merged_data = merge_coincidence_with("example.csv", r"\bmerge\b", &options, &merge).unwrap();
+/// ```
+pub fn merge_coincidence_with<P: Into<Pattern>>(
+    file_path: &str,
+    patron: P,
+    options: &SearchOptions,
+    merge: &MergeOptions,
+) -> Result<String, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let re = options.regex(&patron.into())?;
     let file = File::open(file_path)?;
-    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
-    let mut merged_data: Vec<Vec<String>> = Vec::new();
-
-    for result in rdr.records() {
-        let record = result?;
-        let mut merged_record: Vec<String> = Vec::new();
-        let mut merged = false;
-
-        for field in record.iter() {
-            if re.is_match(field) {
-                merged_record.push("[MERGED]".to_string());
-                merged = true;
-            } else {
-                merged_record.push(field.to_string());
-            }
-        }
-
-        if merged {
-            merged_data.push(merged_record);
-        }
-    }
+    let mut output = Vec::new();
+    merge::merge_records(file, &mut output, &re, options, merge)?;
 
-    let mut wtr = WriterBuilder::new().from_writer(vec![]);
-    for record in merged_data {
-        wtr.write_record(&record)?;
-    }
+    Ok(String::from_utf8(output)?)
+}
 
-    Ok(String::from_utf8(wtr.into_inner()?)?)
+/// Merges the records read from any `std::io::Read` source that match a specific pattern, replacing those matches
+/// as configured by the given [`MergeOptions`], and writes the merged records to any `std::io::Write` sink.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `writer` - The sink receiving the merged CSV records.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read and the merged records are written.
+/// * `merge` - The [`MergeOptions`] describing how the matches are replaced.
+///
+/// # Returns
+///
+/// A `Result` containing the number of fields replaced once every merged record has been written, or an error if
+/// there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read or written, contains malformed records, or if the
+/// regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{merge_coincidence_in, MergeOptions, SearchOptions};
+///
+/// let data = "name,city\nJhon,madrid\nmarta,paris\n";
+/// let (options, merge) = (SearchOptions::default(), MergeOptions::default());
+/// let mut output = Vec::new();
+/// let replaced = merge_coincidence_in(data.as_bytes(), &mut output, r"^[A-Z]", &options, &merge).unwrap();
+///
+/// assert_eq!(replaced, 1);
+/// assert_eq!(String::from_utf8(output).unwrap(), "[MERGED],madrid\n");
+/// ```
+pub fn merge_coincidence_in<R: Read, W: Write, P: Into<Pattern>>(
+    reader: R,
+    writer: W,
+    patron: P,
+    options: &SearchOptions,
+    merge: &MergeOptions,
+) -> Result<usize, CoincidenceError> {
+    let re = options.regex(&patron.into())?;
+    merge::merge_records(reader, writer, &re, options, merge)
 }
 
 /// Validates if the given file path has a ".csv" extension.
@@ -191,33 +525,46 @@ pub fn merge_coincidence(file_path: &str, patron: &str) -> Result<String, Box<dy
 ///
 /// # Returns
 ///
-/// A `Result` containing `Ok(())` if the file has a valid CSV extension, or an error message if the extension is not valid.
+/// A `Result` containing `Ok(())` if the file has a valid CSV extension, or `CoincidenceError::InvalidExtension` if
+/// the extension is not valid.
 ///
-fn validate_csv_extension(file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub(crate) fn validate_csv_extension(file_path: &str) -> Result<(), CoincidenceError> {
     let file_extension = Path::new(file_path)
         .extension()
         .and_then(|ext| ext.to_str());
 
     match file_extension {
         Some("csv") => Ok(()),
-        _ => Err("The file is not a valid CSV file".into()),
+        _ => Err(CoincidenceError::InvalidExtension(file_path.into())),
     }
 }
 
+/// Compiles the given regular expression pattern.
+///
+/// # Arguments
+///
+/// * `pattern` - A string slice representing the regular expression pattern to compile.
+///
+/// # Returns
+///
+/// A `Result` containing the compiled `Regex`, or `CoincidenceError::Regex` if the pattern is invalid.
+///
+pub(crate) fn compile_pattern(pattern: &str) -> Result<Regex, CoincidenceError> {
+    Ok(Regex::new(pattern)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const TEST_DATA: &str = "name,email,city\nJhon,jhon@example.com,madrid\nMarta,marta@example.com,paris\n";
+
     #[test]
     fn test_find_partial_matches() {
-        let file_path = "test_data.csv";
         let regex_pattern = r"^[A-Z][a-z]*";
 
-        let matches = find_partial_matches(file_path, regex_pattern).unwrap();
-        let mut expected_matches_results = Vec::<String>::new();
-
-        expected_matches_results.push("Jhon".to_string());
-        expected_matches_results.push("Marta".to_string());
+        let matches = find_partial_matches_in(TEST_DATA.as_bytes(), regex_pattern, &SearchOptions::default()).unwrap();
+        let expected_matches_results = vec!["Jhon".to_string(), "Marta".to_string()];
 
         assert_eq!(expected_matches_results, matches);
     }
@@ -233,19 +580,17 @@ mod tests {
 
     #[test]
     fn test_find_partial_matches_no_matches() {
-        let file_path = "test_data.csv";
         let regex_pattern = r"^[0-9]+";
 
-        let matches = find_partial_matches(file_path, regex_pattern).unwrap();
+        let matches = find_partial_matches_in(TEST_DATA.as_bytes(), regex_pattern, &SearchOptions::default()).unwrap();
         assert_eq!(matches, Vec::<String>::new());
     }
 
     #[test]
     fn test_find_partial_matches_empty_file() {
-        let file_path = "empty.csv";
         let regex_pattern = r"^[A-Z][a-z]*";
 
-        let matches = find_partial_matches(file_path, regex_pattern).unwrap();
+        let matches = find_partial_matches_in("".as_bytes(), regex_pattern, &SearchOptions::default()).unwrap();
         assert_eq!(matches, Vec::<String>::new());
     }
 
@@ -257,4 +602,121 @@ mod tests {
         let result = find_partial_matches(file_path, regex_pattern);
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_find_matches_in_reports_location() {
+        let matches = find_matches_in(TEST_DATA.as_bytes(), r"marta@", &SearchOptions::default()).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        let m = &matches[0];
+        assert_eq!((m.record, m.line, m.column), (2, 3, 1));
+        assert_eq!(m.byte, TEST_DATA.find("Marta").unwrap() as u64);
+        assert_eq!(m.header.as_deref(), Some("email"));
+        assert_eq!(m.value, "marta@example.com");
+        assert_eq!((m.start, m.end), (0, 6));
+        assert_eq!(m.as_str(), "marta@");
+    }
+
+    #[test]
+    fn test_count_coincidences_in() {
+        let count = count_coincidences_in(TEST_DATA.as_bytes(), r"@example\.com$", &SearchOptions::default()).unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_merge_coincidence_in() {
+        let mut output = Vec::new();
+        let (options, merge) = (SearchOptions::default(), MergeOptions::default());
+        let replaced = merge_coincidence_in(TEST_DATA.as_bytes(), &mut output, r"^Marta$", &options, &merge).unwrap();
+
+        assert_eq!(replaced, 1);
+        assert_eq!(String::from_utf8(output).unwrap(), "[MERGED],marta@example.com,paris\n");
+    }
+
+    #[test]
+    fn test_find_matches_without_headers() {
+        let options = SearchOptions::new().has_headers(false);
+        let matches = find_matches_in(TEST_DATA.as_bytes(), r"^name$", &options).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].record, matches[0].header.as_deref()), (1, None));
+    }
+
+    #[test]
+    fn test_merge_coincidence_keeps_delimiter() {
+        let data = "name;city\nJhon;madrid\nMarta;paris\n";
+        let options = SearchOptions::new().delimiter(b';');
+        let mut output = Vec::new();
+        merge_coincidence_in(data.as_bytes(), &mut output, r"^madrid$", &options, &MergeOptions::default()).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "Jhon;[MERGED]\n");
+    }
+
+    #[test]
+    fn test_column_selection_applies_to_all_entry_points() {
+        let data = "name,city\nJhon,Madrid\nmarta,Paris\n";
+        let options = SearchOptions::new().column("name");
+
+        let matches = find_partial_matches_in(data.as_bytes(), r"^[A-Z][a-z]*", &options).unwrap();
+        assert_eq!(matches, vec!["Jhon"]);
+
+        let count = count_coincidences_in(data.as_bytes(), r"^[A-Z][a-z]*", &options).unwrap();
+        assert_eq!(count, 1);
+
+        let mut output = Vec::new();
+        merge_coincidence_in(data.as_bytes(), &mut output, r"^[A-Z][a-z]*", &options, &MergeOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "[MERGED],Madrid\n");
+    }
+
+    #[test]
+    fn test_unknown_column_is_reported() {
+        let options = SearchOptions::new().exclude_column("phone");
+        let result = count_coincidences_in(TEST_DATA.as_bytes(), r"^[A-Z]", &options);
+
+        assert!(matches!(result, Err(CoincidenceError::UnknownColumn(name)) if name == "phone"));
+    }
+
+    #[test]
+    fn test_match_mode_applies_to_all_entry_points() {
+        let data = "code\nabc\nxabcx\n";
+        let options = SearchOptions::new().match_mode(MatchMode::FullField);
+
+        assert_eq!(find_partial_matches_in(data.as_bytes(), "abc", &options).unwrap(), vec!["abc"]);
+        assert_eq!(count_coincidences_in(data.as_bytes(), "abc", &options).unwrap(), 1);
+
+        let mut output = Vec::new();
+        merge_coincidence_in(data.as_bytes(), &mut output, "abc", &options, &MergeOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "[MERGED]\n");
+    }
+
+    #[test]
+    fn test_find_partial_matches_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("people.csv");
+        std::fs::write(&file_path, TEST_DATA).unwrap();
+
+        let matches = find_partial_matches(file_path.to_str().unwrap(), r"^[A-Z][a-z]*");
+        assert_eq!(matches.unwrap(), vec!["Jhon".to_string(), "Marta".to_string()]);
+    }
+
+    #[test]
+    fn test_invalid_extension_is_reported() {
+        let result = count_coincidences("test_data.txt", r"^[A-Z][a-z]*");
+        assert!(matches!(result, Err(CoincidenceError::InvalidExtension(_))));
+
+        let result = merge_coincidence("test_data.txt", r"^[A-Z][a-z]*");
+        assert!(matches!(result, Err(CoincidenceError::InvalidExtension(_))));
+    }
+
+    #[test]
+    fn test_empty_pattern_matches_every_field() {
+        let count = count_coincidences_in(TEST_DATA.as_bytes(), "", &SearchOptions::default()).unwrap();
+        assert_eq!(count, 6);
+    }
+
+    #[test]
+    fn test_missing_file_is_io_error() {
+        let result = find_partial_matches("missing.csv", r"^[A-Z][a-z]*");
+        assert!(matches!(result, Err(CoincidenceError::Io(_))));
+    }
+}
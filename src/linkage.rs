@@ -0,0 +1,448 @@
+use crate::columns::{resolve_columns, ColumnSelector};
+use crate::cross::key;
+use crate::{validate_csv_extension, CoincidenceError, SearchOptions};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+
+/// A string similarity measure, scoring a pair of values between `0.0` (nothing in common) and `1.0` (identical).
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::Similarity;
+///
+/// assert_eq!(Similarity::Levenshtein.score("Jon Smith", "Jhon Smith"), 0.9);
+/// assert!(Similarity::JaroWinkler.score("Jon Smith", "Jhon Smith") > 0.95);
+/// assert_eq!(Similarity::Trigram.score("abc", "xyz"), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Similarity {
+    /// One minus the Levenshtein edit distance divided by the length of the longer value, in characters.
+    Levenshtein,
+    /// The Jaro-Winkler similarity, which favors values sharing a common prefix.
+    #[default]
+    JaroWinkler,
+    /// The Jaccard similarity of the sets of character trigrams of both values, padded so short values still yield
+    /// trigrams.
+    Trigram,
+}
+
+impl Similarity {
+    /// Returns the similarity of the two values. Two empty values are identical.
+    pub fn score(self, a: &str, b: &str) -> f64 {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+
+        if a == b {
+            return 1.0;
+        }
+
+        match self {
+            Similarity::Levenshtein => 1.0 - levenshtein(&a, &b) as f64 / a.len().max(b.len()) as f64,
+            Similarity::JaroWinkler => jaro_winkler(&a, &b),
+            Similarity::Trigram => trigram(&a, &b),
+        }
+    }
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+fn jaro(a: &[char], b: &[char]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+
+    for (i, ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+
+        for j in start..end {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let a_order = a.iter().zip(&a_matched).filter(|(_, &m)| m).map(|(c, _)| c);
+    let b_order = b.iter().zip(&b_matched).filter(|(_, &m)| m).map(|(c, _)| c);
+    let transpositions = a_order.zip(b_order).filter(|(x, y)| x != y).count() / 2;
+
+    let m = matches as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0
+}
+
+fn jaro_winkler(a: &[char], b: &[char]) -> f64 {
+    let jaro = jaro(a, b);
+    let prefix = a.iter().zip(b).take(4).take_while(|(x, y)| x == y).count();
+
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+fn trigram(a: &[char], b: &[char]) -> f64 {
+    let trigrams = |chars: &[char]| -> HashSet<[char; 3]> {
+        let padded: Vec<char> = [' ', ' '].iter().chain(chars).chain([' '].iter()).copied().collect();
+        padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+    };
+
+    let a = trigrams(a);
+    let b = trigrams(b);
+    let shared = a.intersection(&b).count();
+
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Configures how the linkage functions compare records.
+///
+/// The default scores pairs with [`Similarity::JaroWinkler`], keeps those scoring at least `0.85` and compares every
+/// left record with every right record.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{LinkOptions, Similarity};
+///
+/// let link = LinkOptions::new()
+///     .similarity(Similarity::Trigram)
+///     .threshold(0.6)
+///     .case_insensitive(true)
+///     .block_on("zip", "postal_code");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LinkOptions {
+    pub(crate) similarity: Similarity,
+    pub(crate) threshold: f64,
+    pub(crate) case_insensitive: bool,
+    pub(crate) left_blocks: Vec<ColumnSelector>,
+    pub(crate) right_blocks: Vec<ColumnSelector>,
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        LinkOptions {
+            similarity: Similarity::JaroWinkler,
+            threshold: 0.85,
+            case_insensitive: false,
+            left_blocks: Vec::new(),
+            right_blocks: Vec::new(),
+        }
+    }
+}
+
+impl LinkOptions {
+    /// Creates a new set of linkage options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the similarity measure. The default is [`Similarity::JaroWinkler`].
+    pub fn similarity(mut self, similarity: Similarity) -> Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// Sets the minimum score of a reported pair, between `0.0` and `1.0`. The default is `0.85`.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets whether values are lowercased before being compared. The default is `false`.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Adds a blocking key: only records with exactly the same values in every blocking column pair are compared,
+    /// which avoids comparing every left record with every right record.
+    pub fn block_on<L: Into<ColumnSelector>, R: Into<ColumnSelector>>(mut self, left: L, right: R) -> Self {
+        self.left_blocks.push(left.into());
+        self.right_blocks.push(right.into());
+        self
+    }
+}
+
+/// A candidate pair of records found by the linkage functions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkPair {
+    /// The 1-based number of the left data record.
+    pub left: u64,
+    /// The 1-based number of the right data record.
+    pub right: u64,
+    /// The score of the pair: the mean of the per-column scores.
+    pub score: f64,
+    /// The score of each compared column pair, in the order the columns were given.
+    pub scores: Vec<f64>,
+}
+
+/// Finds the records of two CSV files whose selected columns are similar.
+///
+/// # Arguments
+///
+/// * `left_path` - A string slice representing the file path to the left CSV file.
+/// * `right_path` - A string slice representing the file path to the right CSV file.
+/// * `left_columns` - The columns of the left file to compare.
+/// * `right_columns` - The columns of the right file to compare, in the same order as `left_columns`.
+/// * `options` - The [`SearchOptions`] describing how both files are read and which of their records are linked.
+/// * `link` - The [`LinkOptions`] describing the similarity measure, threshold and blocking keys.
+///
+/// # Returns
+///
+/// A `Result` containing the [`LinkPair`] values scoring at least the threshold, in left file order and then right
+/// file order, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either file is not a valid CSV file, cannot be read, contains malformed records,
+/// if a compared or blocking column does not exist or if the two sides select a different number of columns.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{link_records, LinkOptions, SearchOptions};
+///
+/// let link = LinkOptions::new().block_on("country", "country");
+/// let pairs = link_records("crm_a.csv", "crm_b.csv", ["name"], ["full_name"], &SearchOptions::default(), &link)
+///     .unwrap();
+/// ```
+pub fn link_records<CL, CR>(
+    left_path: &str,
+    right_path: &str,
+    left_columns: CL,
+    right_columns: CR,
+    options: &SearchOptions,
+    link: &LinkOptions,
+) -> Result<Vec<LinkPair>, CoincidenceError>
+where
+    CL: IntoIterator,
+    CL::Item: Into<ColumnSelector>,
+    CR: IntoIterator,
+    CR::Item: Into<ColumnSelector>,
+{
+    validate_csv_extension(left_path)?;
+    validate_csv_extension(right_path)?;
+
+    let left = File::open(left_path)?;
+    let right = File::open(right_path)?;
+    link_records_in(left, right, left_columns, right_columns, options, link)
+}
+
+/// Finds the records of CSV data read from two `std::io::Read` sources whose selected columns are similar.
+///
+/// The compared and blocking values of the right input are loaded into memory and grouped by blocking key; the left
+/// input is streamed and each record is only compared with the right records of its block.
+///
+/// # Arguments
+///
+/// * `left` - The source of the left CSV data.
+/// * `right` - The source of the right CSV data.
+/// * `left_columns` - The columns of the left data to compare.
+/// * `right_columns` - The columns of the right data to compare, in the same order as `left_columns`.
+/// * `options` - The [`SearchOptions`] describing how both inputs are read and which of their records are linked.
+/// * `link` - The [`LinkOptions`] describing the similarity measure, threshold and blocking keys.
+///
+/// # Returns
+///
+/// A `Result` containing the [`LinkPair`] values scoring at least the threshold, in left input order and then right
+/// input order, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if either input cannot be read, contains malformed records, if a compared or
+/// blocking column does not exist or if the two sides select a different number of columns.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{link_records_in, LinkOptions, SearchOptions};
+///
+/// let crm_a = "name,city\nJhon Smith,Madrid\nMarta Lopez,Paris\n";
+/// let crm_b = "full_name,city\nJon Smith,Madrid\nMarta Lopez,Madrid\n";
+/// let link = LinkOptions::new().threshold(0.9).block_on("city", "city");
+/// let options = SearchOptions::default();
+///
+/// let pairs = link_records_in(crm_a.as_bytes(), crm_b.as_bytes(), ["name"], ["full_name"], &options, &link).unwrap();
+///
+/// assert_eq!(pairs.len(), 1);
+/// assert_eq!((pairs[0].left, pairs[0].right), (1, 1));
+/// ```
+pub fn link_records_in<L, R, CL, CR>(
+    left: L,
+    right: R,
+    left_columns: CL,
+    right_columns: CR,
+    options: &SearchOptions,
+    link: &LinkOptions,
+) -> Result<Vec<LinkPair>, CoincidenceError>
+where
+    L: Read,
+    R: Read,
+    CL: IntoIterator,
+    CL::Item: Into<ColumnSelector>,
+    CR: IntoIterator,
+    CR::Item: Into<ColumnSelector>,
+{
+    let left_columns: Vec<ColumnSelector> = left_columns.into_iter().map(Into::into).collect();
+    let right_columns: Vec<ColumnSelector> = right_columns.into_iter().map(Into::into).collect();
+
+    let mut left_rdr = options.reader_builder().from_reader(left);
+    let mut right_rdr = options.reader_builder().from_reader(right);
+    let left_headers = options.read_headers(&mut left_rdr)?;
+    let right_headers = options.read_headers(&mut right_rdr)?;
+
+    let left_compared = resolve_columns(&left_columns, left_headers.as_ref())?;
+    let right_compared = resolve_columns(&right_columns, right_headers.as_ref())?;
+    let left_blocks = resolve_columns(&link.left_blocks, left_headers.as_ref())?;
+    let right_blocks = resolve_columns(&link.right_blocks, right_headers.as_ref())?;
+    let left_filter = options.record_filter(left_headers.as_ref())?;
+    let right_filter = options.record_filter(right_headers.as_ref())?;
+
+    for (left, right) in [(&left_compared, &right_compared), (&left_blocks, &right_blocks)] {
+        if left.len() != right.len() {
+            return Err(CoincidenceError::KeyMismatch {
+                left: left.len(),
+                right: right.len(),
+            });
+        }
+    }
+
+    let normalize = |values: Vec<String>| -> Vec<String> {
+        match link.case_insensitive {
+            true => values.iter().map(|value| value.to_lowercase()).collect(),
+            false => values,
+        }
+    };
+
+    let mut blocks: HashMap<Vec<String>, Vec<(u64, Vec<String>)>> = HashMap::new();
+    for (index, result) in right_rdr.records().enumerate() {
+        let record = result?;
+        if !right_filter.is_selected(&record) {
+            continue;
+        }
+        let values = normalize(key(&record, &right_compared)?);
+        blocks
+            .entry(normalize(key(&record, &right_blocks)?))
+            .or_default()
+            .push((index as u64 + 1, values));
+    }
+
+    let mut pairs = Vec::new();
+    for (index, result) in left_rdr.records().enumerate() {
+        let record = result?;
+        if !left_filter.is_selected(&record) {
+            continue;
+        }
+        let Some(candidates) = blocks.get(&normalize(key(&record, &left_blocks)?)) else {
+            continue;
+        };
+        let values = normalize(key(&record, &left_compared)?);
+
+        for (right, right_values) in candidates {
+            let scores: Vec<f64> = values
+                .iter()
+                .zip(right_values)
+                .map(|(a, b)| link.similarity.score(a, b))
+                .collect();
+            let score = match scores.is_empty() {
+                true => 1.0,
+                false => scores.iter().sum::<f64>() / scores.len() as f64,
+            };
+
+            if score >= link.threshold {
+                pairs.push(LinkPair {
+                    left: index as u64 + 1,
+                    right: *right,
+                    score,
+                    scores,
+                });
+            }
+        }
+    }
+
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn test_similarity_reference_values() {
+        assert!(close(Similarity::Levenshtein.score("kitten", "sitting"), 1.0 - 3.0 / 7.0));
+        assert!(close(Similarity::JaroWinkler.score("MARTHA", "MARHTA"), 0.961));
+        assert!(close(Similarity::JaroWinkler.score("DIXON", "DICKSONX"), 0.813));
+        assert!(close(Similarity::Trigram.score("ab", "abc"), 2.0 / 5.0));
+        assert_eq!(Similarity::Levenshtein.score("", ""), 1.0);
+        assert_eq!(Similarity::JaroWinkler.score("", "a"), 0.0);
+        assert!(close(Similarity::Levenshtein.score("niño", "nino"), 0.75));
+    }
+
+    #[test]
+    fn test_multi_column_scores_and_case_folding() {
+        let left = "first,last\nJHON,Smith\nAna,Ruiz\n";
+        let right = "first,last\njon,smith\n";
+        let link = LinkOptions::new()
+            .similarity(Similarity::Levenshtein)
+            .threshold(0.8)
+            .case_insensitive(true);
+        let pairs = link_records_in(left.as_bytes(), right.as_bytes(), 0..2, 0..2, &SearchOptions::default(), &link)
+            .unwrap();
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].scores, vec![0.75, 1.0]);
+        assert!(close(pairs[0].score, 0.875));
+    }
+
+    #[test]
+    fn test_blocking_limits_comparisons() {
+        let left = "name,zip\nJhon,1\n";
+        let right = "name,zip\nJhon,2\nJhon,1\n";
+        let link = LinkOptions::new().block_on("zip", "zip");
+        let options = SearchOptions::default();
+        let pairs = link_records_in(left.as_bytes(), right.as_bytes(), ["name"], ["name"], &options, &link).unwrap();
+
+        assert_eq!(pairs.iter().map(|p| p.right).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_filtered_records_are_not_linked() {
+        let left = "name,active\nJhon,yes\nJohn,no\n";
+        let right = "name,active\nJhon,no\nJohn,yes\n";
+        let options = SearchOptions::new().filter(Query::parse("active = 'yes'").unwrap());
+        let link = LinkOptions::new().threshold(0.5);
+        let pairs = link_records_in(left.as_bytes(), right.as_bytes(), ["name"], ["name"], &options, &link).unwrap();
+
+        assert_eq!(pairs.iter().map(|p| (p.left, p.right)).collect::<Vec<_>>(), vec![(1, 2)]);
+    }
+}
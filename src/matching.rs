@@ -0,0 +1,51 @@
+use csv::StringRecord;
+use std::ops::Range;
+
+/// A single coincidence found in a CSV field, with enough context to locate the exact cell again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// The 1-based number of the data record containing the match (the header row is not counted).
+    pub record: u64,
+    /// The 1-based line number where the record starts in the input.
+    pub line: u64,
+    /// The byte offset where the record starts in the input.
+    pub byte: u64,
+    /// The zero-based index of the column containing the match.
+    pub column: usize,
+    /// The header name of the column, when the input has a header row.
+    pub header: Option<String>,
+    /// The full value of the matching field.
+    pub value: String,
+    /// The byte offset within `value` where the match starts.
+    pub start: usize,
+    /// The byte offset within `value` where the match ends.
+    pub end: usize,
+}
+
+impl Match {
+    pub(crate) fn new(
+        record_number: u64,
+        record: &StringRecord,
+        column: usize,
+        headers: Option<&StringRecord>,
+        span: Range<usize>,
+    ) -> Self {
+        let position = record.position().cloned().unwrap_or_else(csv::Position::new);
+
+        Match {
+            record: record_number,
+            line: position.line(),
+            byte: position.byte(),
+            column,
+            header: headers.and_then(|h| h.get(column)).map(str::to_string),
+            value: record.get(column).unwrap_or_default().to_string(),
+            start: span.start,
+            end: span.end,
+        }
+    }
+
+    /// Returns the portion of the field that matched the pattern.
+    pub fn as_str(&self) -> &str {
+        &self.value[self.start..self.end]
+    }
+}
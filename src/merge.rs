@@ -0,0 +1,516 @@
+use crate::atomic::write_atomically;
+use crate::{validate_csv_extension, CoincidenceError, Match, Pattern, SearchOptions};
+use csv::StringRecord;
+use regex::{Captures, NoExpand, Regex};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The text written in place of a match by the merge functions.
+#[derive(Clone)]
+pub enum Replacement {
+    /// A fixed string, written as is.
+    Fixed(String),
+    /// A `regex` replacement template that may refer to capture groups with `$1`, `$name` or `${name}`.
+    Template(String),
+    /// A closure computing the replacement from the match.
+    With(Arc<dyn Fn(&Match) -> String + Send + Sync>),
+}
+
+impl Replacement {
+    /// Creates a replacement writing the given string as is.
+    pub fn fixed<S: Into<String>>(text: S) -> Self {
+        Replacement::Fixed(text.into())
+    }
+
+    /// Creates a replacement expanding the given `regex` template, e.g. `"$last, $first"`.
+    pub fn template<S: Into<String>>(template: S) -> Self {
+        Replacement::Template(template.into())
+    }
+
+    /// Creates a replacement computed by the given closure.
+    pub fn with<F>(f: F) -> Self
+    where
+        F: Fn(&Match) -> String + Send + Sync + 'static,
+    {
+        Replacement::With(Arc::new(f))
+    }
+}
+
+impl Default for Replacement {
+    fn default() -> Self {
+        Replacement::Fixed("[MERGED]".to_string())
+    }
+}
+
+impl fmt::Debug for Replacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Replacement::Fixed(text) => f.debug_tuple("Fixed").field(text).finish(),
+            Replacement::Template(template) => f.debug_tuple("Template").field(template).finish(),
+            Replacement::With(_) => f.write_str("With(..)"),
+        }
+    }
+}
+
+/// Which part of a matching field the merge functions replace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplaceScope {
+    /// The whole field is replaced. Templates and closures see the first match in the field.
+    #[default]
+    Field,
+    /// Only the matched text is replaced, for every non-overlapping match in the field.
+    Matched,
+}
+
+/// Which records the merge functions write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeMode {
+    /// Only the records with at least one replaced field are written, without the header row.
+    #[default]
+    MatchedOnly,
+    /// Every record is written, preceded by the header row when the input has one, so the output keeps the schema of
+    /// the input and can replace it.
+    FullFile,
+}
+
+/// Configures what the merge functions write in place of the matches, and which records they write.
+///
+/// The default replaces every matching field with `"[MERGED]"` and writes only the records that had a match.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{merge_coincidence_in, MergeOptions, Replacement, ReplaceScope, SearchOptions};
+///
+/// let data = "name,phone\nJhon,555-1234\n";
+/// let merge = MergeOptions::new()
+///     .replacement(Replacement::template("$area-XXXX"))
+///     .scope(ReplaceScope::Matched);
+/// let mut output = Vec::new();
+/// merge_coincidence_in(data.as_bytes(), &mut output, r"(?P<area>\d{3})-\d{4}", &SearchOptions::default(), &merge)
+///     .unwrap();
+///
+/// assert_eq!(String::from_utf8(output).unwrap(), "Jhon,555-XXXX\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    pub(crate) replacement: Replacement,
+    pub(crate) scope: ReplaceScope,
+    pub(crate) mode: MergeMode,
+    pub(crate) backup: bool,
+}
+
+impl MergeOptions {
+    /// Creates a new set of merge options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the text written in place of the matches. The default is `Replacement::Fixed("[MERGED]")`.
+    pub fn replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Sets which part of a matching field is replaced. The default is [`ReplaceScope::Field`].
+    pub fn scope(mut self, scope: ReplaceScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Sets which records are written. The default is [`MergeMode::MatchedOnly`].
+    pub fn mode(mut self, mode: MergeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets whether [`merge_coincidence_in_place`] keeps a copy of the original file, named after it with a `.bak`
+    /// suffix (e.g. `data.csv.bak`). The default is `false`.
+    pub fn backup(mut self, yes: bool) -> Self {
+        self.backup = yes;
+        self
+    }
+
+    /// Returns the replacement for the given field, or `None` if the field does not match.
+    fn replace<'f>(
+        &self,
+        re: &Regex,
+        field: &'f str,
+        record_number: u64,
+        record: &StringRecord,
+        column: usize,
+        headers: Option<&StringRecord>,
+    ) -> Option<Cow<'f, str>> {
+        let found = re.find(field)?;
+        let to_match = |range| Match::new(record_number, record, column, headers, range);
+
+        let replaced = match (self.scope, &self.replacement) {
+            (ReplaceScope::Field, Replacement::Fixed(text)) => Cow::Owned(text.clone()),
+            (ReplaceScope::Field, Replacement::Template(template)) => {
+                let mut expanded = String::new();
+                if let Some(caps) = re.captures(field) {
+                    caps.expand(template, &mut expanded);
+                }
+                Cow::Owned(expanded)
+            }
+            (ReplaceScope::Field, Replacement::With(f)) => Cow::Owned(f(&to_match(found.range()))),
+            (ReplaceScope::Matched, Replacement::Fixed(text)) => re.replace_all(field, NoExpand(text)),
+            (ReplaceScope::Matched, Replacement::Template(template)) => re.replace_all(field, template.as_str()),
+            (ReplaceScope::Matched, Replacement::With(f)) => {
+                re.replace_all(field, |caps: &Captures| f(&to_match(caps.get(0).map_or(0..0, |m| m.range()))))
+            }
+        };
+
+        Some(replaced)
+    }
+}
+
+/// Merges the records in a CSV file that match a specific pattern and streams the merged records to any
+/// `std::io::Write` sink as they are produced, so memory use does not grow with the input size.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `writer` - The sink receiving the merged CSV records.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read and the merged records are written.
+/// * `merge` - The [`MergeOptions`] describing how the matches are replaced.
+///
+/// # Returns
+///
+/// A `Result` containing the number of fields replaced once every merged record has been written and the sink
+/// flushed, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// if the sink cannot be written, or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{merge_coincidence_to_writer, MergeOptions, SearchOptions};
+///
+/// let stdout = std::io::stdout();
+/// merge_coincidence_to_writer("huge.csv", stdout.lock(), r"\d{16}", &SearchOptions::default(), &MergeOptions::default())
+///     .unwrap();
+/// ```
+pub fn merge_coincidence_to_writer<W: Write, P: Into<Pattern>>(
+    file_path: &str,
+    writer: W,
+    pattern: P,
+    options: &SearchOptions,
+    merge: &MergeOptions,
+) -> Result<usize, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let re = options.regex(&pattern.into())?;
+    let file = File::open(file_path)?;
+    merge_records(file, writer, &re, options, merge)
+}
+
+/// Merges the records in a CSV file that match a specific pattern and writes the merged records to another file.
+///
+/// `output_path` is replaced atomically, so it may be the input file itself and a failure leaves it untouched.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `output_path` - A string slice representing the file path the merged CSV data is written to.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read and the merged records are written.
+/// * `merge` - The [`MergeOptions`] describing how the matches are replaced.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once the output file is in place, or an error if there is any issue during
+/// processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the input is not a valid CSV file, cannot be read, contains malformed records,
+/// if the output cannot be written, or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{merge_coincidence_to_path, MergeMode, MergeOptions, SearchOptions};
+///
+/// let merge = MergeOptions::new().mode(MergeMode::FullFile);
+/// merge_coincidence_to_path("customers.csv", "customers_clean.csv", r"@", &SearchOptions::default(), &merge).unwrap();
+/// ```
+pub fn merge_coincidence_to_path<P: Into<Pattern>>(
+    file_path: &str,
+    output_path: &str,
+    pattern: P,
+    options: &SearchOptions,
+    merge: &MergeOptions,
+) -> Result<(), CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let re = options.regex(&pattern.into())?;
+    let file = File::open(file_path)?;
+    write_atomically(Path::new(output_path), |output| merge_records(file, output, &re, options, merge).map(drop))
+}
+
+/// Merges the records in a CSV file that match a specific pattern and replaces the file with the result.
+///
+/// The file is replaced atomically and keeps its permissions; on error it keeps its previous content. When
+/// [`MergeOptions::backup`] is enabled, a copy of the original is first written, just as atomically, next to it with
+/// a `.bak` suffix.
+///
+/// Use [`MergeMode::FullFile`] to keep the header row and the records without matches.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the CSV file to rewrite.
+/// * `pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read and the merged records are written.
+/// * `merge` - The [`MergeOptions`] describing how the matches are replaced.
+///
+/// # Returns
+///
+/// A `Result` containing `Ok(())` once the file has been replaced, or an error if there is any issue during
+/// processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read or replaced, contains malformed
+/// records, if the backup cannot be written, or if the regular expression pattern is invalid.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{merge_coincidence_in_place, MergeMode, MergeOptions, Replacement, SearchOptions};
+///
+/// let merge = MergeOptions::new()
+///     .mode(MergeMode::FullFile)
+///     .replacement(Replacement::fixed("REDACTED"))
+///     .backup(true);
+/// merge_coincidence_in_place("customers.csv", r"^\d{3}-\d{2}-\d{4}$", &SearchOptions::default(), &merge).unwrap();
+/// ```
+pub fn merge_coincidence_in_place<P: Into<Pattern>>(
+    file_path: &str,
+    pattern: P,
+    options: &SearchOptions,
+    merge: &MergeOptions,
+) -> Result<(), CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let re = options.regex(&pattern.into())?;
+    let path = Path::new(file_path);
+    let file = File::open(path)?;
+
+    if merge.backup {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(".bak");
+        write_atomically(Path::new(&backup_path), |backup| {
+            let mut original = File::open(path)?;
+            backup.set_permissions(original.metadata()?.permissions())?;
+            io::copy(&mut original, backup)?;
+            Ok(())
+        })?;
+    }
+
+    write_atomically(path, |output| merge_records(file, output, &re, options, merge).map(drop))
+}
+
+/// Merges the records read from `reader` into `writer`, returning the number of fields replaced.
+pub(crate) fn merge_records<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    re: &Regex,
+    options: &SearchOptions,
+    merge: &MergeOptions,
+) -> Result<usize, CoincidenceError> {
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let mut wtr = options.writer_builder().from_writer(writer);
+    let headers = options.read_headers(&mut rdr)?;
+    let filter = options.column_filter(headers.as_ref())?;
+    let record_filter = options.record_filter(headers.as_ref())?;
+
+    if merge.mode == MergeMode::FullFile {
+        if let Some(headers) = headers.as_ref().filter(|h| !h.is_empty()) {
+            wtr.write_record(headers)?;
+        }
+    }
+
+    let mut replaced_fields = 0;
+    for (index, result) in rdr.records().enumerate() {
+        let record = result?;
+        let selected = record_filter.is_selected(&record);
+        let mut merged_record: Vec<Cow<str>> = Vec::with_capacity(record.len());
+        let mut merged = false;
+
+        for (column, field) in record.iter().enumerate() {
+            let replaced = if selected && filter.is_selected(column) {
+                merge.replace(re, field, index as u64 + 1, &record, column, headers.as_ref())
+            } else {
+                None
+            };
+
+            match replaced {
+                Some(replaced) => {
+                    merged_record.push(replaced);
+                    merged = true;
+                    replaced_fields += 1;
+                }
+                None => merged_record.push(Cow::Borrowed(field)),
+            }
+        }
+
+        if merged || merge.mode == MergeMode::FullFile {
+            wtr.write_record(merged_record.iter().map(|field| field.as_bytes()))?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(replaced_fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTACTS: &str = "name,email\nJhon Smith,jhon@example.com\nMarta Lopez,marta@test.org\n";
+
+    fn merge_contacts(pattern: &str, merge: &MergeOptions) -> String {
+        let re = Regex::new(pattern).unwrap();
+        let mut output = Vec::new();
+        merge_records(CONTACTS.as_bytes(), &mut output, &re, &SearchOptions::default(), merge).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_default_replaces_whole_field() {
+        assert_eq!(merge_contacts(r"@test\.org$", &MergeOptions::new()), "Marta Lopez,[MERGED]\n");
+    }
+
+    #[test]
+    fn test_full_file_keeps_headers_and_unmatched_rows() {
+        let options = MergeOptions::new().mode(MergeMode::FullFile);
+
+        assert_eq!(
+            merge_contacts(r"@test\.org$", &options),
+            "name,email\nJhon Smith,jhon@example.com\nMarta Lopez,[MERGED]\n"
+        );
+    }
+
+    #[test]
+    fn test_full_file_on_empty_input() {
+        let re = Regex::new("x").unwrap();
+        let mut output = Vec::new();
+        let options = MergeOptions::new().mode(MergeMode::FullFile);
+        merge_records("".as_bytes(), &mut output, &re, &SearchOptions::default(), &options).unwrap();
+
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_merge_to_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.csv");
+        let output = dir.path().join("output.csv");
+        std::fs::write(&input, CONTACTS).unwrap();
+
+        let options = MergeOptions::new().mode(MergeMode::FullFile);
+        merge_coincidence_to_path(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            r"^jhon@",
+            &SearchOptions::default(),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "name,email\nJhon Smith,[MERGED]\nMarta Lopez,marta@test.org\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_to_path_failure_keeps_existing_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.csv");
+        let output = dir.path().join("output.csv");
+        std::fs::write(&input, "a,b\n1,2\n3\n").unwrap();
+        std::fs::write(&output, "previous").unwrap();
+
+        let result = merge_coincidence_to_path(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            r"\d",
+            &SearchOptions::default(),
+            &MergeOptions::default(),
+        );
+
+        assert!(matches!(result, Err(CoincidenceError::Csv { .. })));
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "previous");
+    }
+
+    #[test]
+    fn test_merge_in_place_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv");
+        std::fs::write(&path, CONTACTS).unwrap();
+
+        let options = MergeOptions::new().mode(MergeMode::FullFile).backup(true);
+        merge_coincidence_in_place(path.to_str().unwrap(), r"^marta@", &SearchOptions::default(), &options).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "name,email\nJhon Smith,jhon@example.com\nMarta Lopez,[MERGED]\n"
+        );
+        assert_eq!(std::fs::read_to_string(dir.path().join("data.csv.bak")).unwrap(), CONTACTS);
+    }
+
+    #[test]
+    fn test_merge_in_place_failure_keeps_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv");
+        let original = "a,b\n1,2\n3\n";
+        std::fs::write(&path, original).unwrap();
+
+        let result = merge_coincidence_in_place(
+            path.to_str().unwrap(),
+            r"\d",
+            &SearchOptions::default(),
+            &MergeOptions::new().mode(MergeMode::FullFile),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_template_on_whole_field() {
+        let options = MergeOptions::new().replacement(Replacement::template("$last, $first"));
+        let output = merge_contacts(r"^(?P<first>\w+) (?P<last>\w+)$", &options);
+
+        assert_eq!(output, "\"Smith, Jhon\",jhon@example.com\n\"Lopez, Marta\",marta@test.org\n");
+    }
+
+    #[test]
+    fn test_fixed_on_matched_text_is_not_expanded() {
+        let options = MergeOptions::new()
+            .replacement(Replacement::fixed("$user"))
+            .scope(ReplaceScope::Matched);
+
+        assert_eq!(merge_contacts(r"^[a-z]+@", &options), "Jhon Smith,$userexample.com\nMarta Lopez,$usertest.org\n");
+    }
+
+    #[test]
+    fn test_closure_sees_match() {
+        let options = MergeOptions::new()
+            .replacement(Replacement::with(|m: &Match| format!("{}#{}", m.as_str().to_uppercase(), m.record)))
+            .scope(ReplaceScope::Matched);
+
+        assert_eq!(merge_contacts(r"\bmarta\b", &options), "Marta Lopez,MARTA#2@test.org\n");
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{compile_pattern, count, validate_csv_extension, CoincidenceError, MatchMode, SearchOptions};
+use regex::{Regex, RegexSet};
+use std::fs::File;
+use std::io::Read;
+
+/// A set of regular expression patterns searched together in a single pass, each with a label.
+///
+/// Every pattern is anchored according to the [`MatchMode`] of the [`SearchOptions`] it is searched with.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_many_in, PatternSet, SearchOptions};
+///
+/// let patterns = PatternSet::labeled([
+///     ("email", r"^[^@]+@[^@]+$"),
+///     ("phone", r"^\d{3}-\d{4}$"),
+/// ]).unwrap();
+///
+/// let data = "name,contact\nJhon,jhon@example.com\nMarta,555-1234\n";
+/// let matches = find_many_in(data.as_bytes(), &patterns, &SearchOptions::default()).unwrap();
+///
+/// assert_eq!(matches[0].labels, vec!["email"]);
+/// assert_eq!(matches[1].labels, vec!["phone"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    patterns: Vec<String>,
+    compiled: CompiledSet,
+    labels: Vec<String>,
+}
+
+/// A [`PatternSet`] compiled for one match mode.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledSet {
+    set: RegexSet,
+    pub(crate) regexes: Vec<Regex>,
+}
+
+impl PatternSet {
+    /// Creates a pattern set labeled by the patterns themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if any pattern is invalid.
+    pub fn new<I, S>(patterns: I) -> Result<Self, CoincidenceError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns: Vec<String> = patterns.into_iter().map(|p| p.as_ref().to_string()).collect();
+        Self::build(patterns.clone(), patterns)
+    }
+
+    /// Creates a pattern set from `(label, pattern)` pairs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if any pattern is invalid.
+    pub fn labeled<I, L, S>(patterns: I) -> Result<Self, CoincidenceError>
+    where
+        I: IntoIterator<Item = (L, S)>,
+        L: Into<String>,
+        S: AsRef<str>,
+    {
+        let (labels, patterns): (Vec<String>, Vec<String>) = patterns
+            .into_iter()
+            .map(|(label, pattern)| (label.into(), pattern.as_ref().to_string()))
+            .unzip();
+        Self::build(labels, patterns)
+    }
+
+    fn build(labels: Vec<String>, patterns: Vec<String>) -> Result<Self, CoincidenceError> {
+        Ok(PatternSet {
+            compiled: CompiledSet::build(&patterns)?,
+            patterns,
+            labels,
+        })
+    }
+
+    /// Returns the label of the pattern at the given index.
+    pub fn label(&self, index: usize) -> &str {
+        &self.labels[index]
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns `true` if the set contains no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Compiles the patterns anchored according to the match mode.
+    pub(crate) fn compile(&self, mode: MatchMode) -> Result<CompiledSet, CoincidenceError> {
+        if mode == MatchMode::Substring {
+            return Ok(self.compiled.clone());
+        }
+
+        let anchored: Vec<_> = self.patterns.iter().map(|pattern| mode.anchored(pattern)).collect();
+        CompiledSet::build(&anchored)
+    }
+}
+
+impl CompiledSet {
+    fn build<S: AsRef<str>>(patterns: &[S]) -> Result<Self, CoincidenceError> {
+        Ok(CompiledSet {
+            set: RegexSet::new(patterns)?,
+            regexes: patterns.iter().map(|pattern| compile_pattern(pattern.as_ref())).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns the indices of the patterns matching the field, or of those not matching it when `invert` is set.
+    pub(crate) fn matching(&self, field: &str, invert: bool) -> Vec<usize> {
+        let matches = self.set.matches(field);
+        (0..self.regexes.len()).filter(|&pattern| matches.matched(pattern) != invert).collect()
+    }
+}
+
+/// A field matched by one or more patterns of a [`PatternSet`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiMatch {
+    /// The 1-based number of the data record containing the field (the header row is not counted).
+    pub record: u64,
+    /// The 1-based line number where the record starts in the input.
+    pub line: u64,
+    /// The byte offset where the record starts in the input.
+    pub byte: u64,
+    /// The zero-based index of the column containing the field.
+    pub column: usize,
+    /// The header name of the column, when the input has a header row.
+    pub header: Option<String>,
+    /// The full value of the field.
+    pub value: String,
+    /// The indices of the patterns that matched the field, in ascending order.
+    pub patterns: Vec<usize>,
+    /// The labels of the patterns that matched the field, in the same order as `patterns`.
+    pub labels: Vec<String>,
+}
+
+/// The number of fields and occurrences matched by one pattern of a [`PatternSet`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternCount {
+    /// The zero-based index of the pattern in the set.
+    pub pattern: usize,
+    /// The label of the pattern.
+    pub label: String,
+    /// The number of fields the pattern matched.
+    pub count: usize,
+    /// The total number of non-overlapping matches of the pattern.
+    pub occurrences: usize,
+}
+
+/// Searches the CSV file for every pattern of a [`PatternSet`] in a single pass.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `patterns` - The set of patterns to match against the CSV records.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing one [`MultiMatch`] per field matched by at least one pattern, or an error if there is any
+/// issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read or contains malformed records.
+pub fn find_many(
+    file_path: &str,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<Vec<MultiMatch>, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    find_many_in(file, patterns, options)
+}
+
+/// Searches CSV data read from any `std::io::Read` source for every pattern of a [`PatternSet`] in a single pass.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `patterns` - The set of patterns to match against the CSV records.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing one [`MultiMatch`] per field matched by at least one pattern, or an error if there is any
+/// issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read or contains malformed records.
+pub fn find_many_in<R: Read>(
+    reader: R,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<Vec<MultiMatch>, CoincidenceError> {
+    let mut matches = Vec::new();
+
+    scan(reader, patterns, options, |record_number, record, headers, column, matched| {
+        let position = record.position().cloned().unwrap_or_else(csv::Position::new);
+        matches.push(MultiMatch {
+            record: record_number,
+            line: position.line(),
+            byte: position.byte(),
+            column,
+            header: headers.and_then(|h| h.get(column)).map(str::to_string),
+            value: record[column].to_string(),
+            labels: matched.iter().map(|&i| patterns.label(i).to_string()).collect(),
+            patterns: matched,
+        });
+    })?;
+
+    Ok(matches)
+}
+
+/// Counts, in a single pass over the CSV file, the fields matched by each pattern of a [`PatternSet`].
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `patterns` - The set of patterns to match against the CSV records.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing one [`PatternCount`] per pattern, in set order, or an error if there is any issue during
+/// processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read or contains malformed records.
+pub fn count_many(
+    file_path: &str,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<Vec<PatternCount>, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    count_many_in(file, patterns, options)
+}
+
+/// Counts, in a single pass over CSV data read from any `std::io::Read` source, the fields matched by each pattern
+/// of a [`PatternSet`].
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `patterns` - The set of patterns to match against the CSV records.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing one [`PatternCount`] per pattern, in set order, or an error if there is any issue during
+/// processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read or contains malformed records.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_many_in, PatternSet, SearchOptions};
+///
+/// let patterns = PatternSet::labeled([("upper", "^[A-Z]"), ("digits", r"^\d+$")]).unwrap();
+/// let data = "name,age\nJhon,30\nMarta,25\n";
+/// let counts = count_many_in(data.as_bytes(), &patterns, &SearchOptions::default()).unwrap();
+///
+/// assert_eq!((counts[0].label.as_str(), counts[0].count), ("upper", 2));
+/// assert_eq!((counts[1].label.as_str(), counts[1].count), ("digits", 2));
+/// ```
+pub fn count_many_in<R: Read>(
+    reader: R,
+    patterns: &PatternSet,
+    options: &SearchOptions,
+) -> Result<Vec<PatternCount>, CoincidenceError> {
+    Ok(count::count_set_matches(reader, patterns, options)?.patterns)
+}
+
+fn scan<R, F>(reader: R, patterns: &PatternSet, options: &SearchOptions, mut on_match: F) -> Result<(), CoincidenceError>
+where
+    R: Read,
+    F: FnMut(u64, &csv::StringRecord, Option<&csv::StringRecord>, usize, Vec<usize>),
+{
+    let patterns = patterns.compile(options.match_mode)?;
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let headers = options.read_headers(&mut rdr)?;
+    let filter = options.column_filter(headers.as_ref())?;
+    let record_filter = options.record_filter(headers.as_ref())?;
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = result?;
+        if !record_filter.is_selected(&record) {
+            continue;
+        }
+
+        for (column, field) in record.iter().enumerate() {
+            if !filter.is_selected(column) {
+                continue;
+            }
+
+            let matched = patterns.matching(field, options.invert);
+            if !matched.is_empty() {
+                on_match(index as u64 + 1, &record, headers.as_ref(), column, matched);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYMENT_NOTES: &str = "name,card,note\n\
+        Jhon,4111-1111-1111-1111,call 555-1234\n\
+        Marta,none,4111-1111-1111-1111 or 555-9876\n";
+
+    fn patterns() -> PatternSet {
+        PatternSet::labeled([
+            ("credit_card", r"\d{4}-\d{4}-\d{4}-\d{4}"),
+            ("phone", r"\b\d{3}-\d{4}\b"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_many_attributes_patterns() {
+        let matches = find_many_in(PAYMENT_NOTES.as_bytes(), &patterns(), &SearchOptions::default()).unwrap();
+        let summary: Vec<(u64, usize, Vec<usize>)> =
+            matches.iter().map(|m| (m.record, m.column, m.patterns.clone())).collect();
+
+        assert_eq!(summary, vec![(1, 1, vec![0]), (1, 2, vec![1]), (2, 2, vec![0, 1])]);
+        assert_eq!(matches[2].labels, vec!["credit_card", "phone"]);
+    }
+
+    #[test]
+    fn test_inverted_find_many_lists_missing_patterns() {
+        let options = SearchOptions::new().column("note").invert(true);
+        let matches = find_many_in(PAYMENT_NOTES.as_bytes(), &patterns(), &options).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].record, matches[0].labels.clone()), (1, vec!["credit_card".to_string()]));
+    }
+
+    #[test]
+    fn test_patterns_follow_the_match_mode() {
+        let patterns = PatternSet::new([r"\d{3}-\d{4}", "none"]).unwrap();
+        let options = SearchOptions::new().match_mode(MatchMode::FullField);
+        let matches = find_many_in(PAYMENT_NOTES.as_bytes(), &patterns, &options).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].value.as_str(), matches[0].patterns.clone()), ("none", vec![1]));
+
+        let counts = count_many_in(PAYMENT_NOTES.as_bytes(), &patterns, &options).unwrap();
+        assert_eq!((counts[0].count, counts[1].count), (0, 1));
+    }
+
+    #[test]
+    fn test_count_many_totals() {
+        let counts = count_many_in(PAYMENT_NOTES.as_bytes(), &patterns(), &SearchOptions::default()).unwrap();
+        let totals: Vec<(&str, usize, usize)> =
+            counts.iter().map(|c| (c.label.as_str(), c.count, c.occurrences)).collect();
+
+        assert_eq!(totals, vec![("credit_card", 2, 2), ("phone", 2, 2)]);
+    }
+
+    #[test]
+    fn test_unlabeled_patterns_use_their_source() {
+        let patterns = PatternSet::new([r"^\d+$"]).unwrap();
+        assert_eq!(patterns.label(0), r"^\d+$");
+        assert_eq!(PatternSet::new(["a", ""]).unwrap().label(1), "");
+    }
+}
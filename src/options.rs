@@ -0,0 +1,332 @@
+use crate::columns::{ColumnFilter, ColumnSelector};
+use crate::pattern::Matcher;
+use crate::query::RecordFilter;
+use crate::{CoincidenceError, CountMode, MatchMode, Pattern, Query};
+use csv::{Reader, ReaderBuilder, StringRecord, Terminator, Trim, WriterBuilder};
+use regex::Regex;
+use std::io::Read;
+
+/// Configures how CSV data is read (and written back, for merges) by the search, count and merge functions.
+///
+/// The defaults match the behavior of the functions without options: comma delimited, double quoted fields, a
+/// header row, no trimming, records that must all have the same number of fields, every column searched and patterns
+/// matching anywhere in a field.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_coincidences_in, SearchOptions, Trim};
+///
+/// let data = "Jhon ; madrid\nMarta ; paris\n";
+/// let options = SearchOptions::new()
+///     .delimiter(b';')
+///     .has_headers(false)
+///     .trim(Trim::All);
+///
+/// assert_eq!(count_coincidences_in(data.as_bytes(), r"^[A-Z][a-z]+$", &options).unwrap(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub(crate) delimiter: u8,
+    pub(crate) quote: u8,
+    pub(crate) quoting: bool,
+    pub(crate) escape: Option<u8>,
+    pub(crate) double_quote: bool,
+    pub(crate) comment: Option<u8>,
+    pub(crate) has_headers: bool,
+    pub(crate) flexible: bool,
+    pub(crate) trim: Trim,
+    pub(crate) terminator: Terminator,
+    pub(crate) columns: Vec<ColumnSelector>,
+    pub(crate) exclude_columns: Vec<ColumnSelector>,
+    pub(crate) match_mode: MatchMode,
+    pub(crate) count_mode: CountMode,
+    pub(crate) top_values: usize,
+    pub(crate) invert: bool,
+    pub(crate) filters: Vec<Query>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            delimiter: b',',
+            quote: b'"',
+            quoting: true,
+            escape: None,
+            double_quote: true,
+            comment: None,
+            has_headers: true,
+            flexible: false,
+            trim: Trim::None,
+            terminator: Terminator::CRLF,
+            columns: Vec::new(),
+            exclude_columns: Vec::new(),
+            match_mode: MatchMode::Substring,
+            count_mode: CountMode::Fields,
+            top_values: 0,
+            invert: false,
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Creates a new set of options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the field delimiter. The default is `b','`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the quote character. The default is `b'"'`.
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Enables or disables quote handling when reading. When disabled, quote characters are treated as regular data.
+    pub fn quoting(mut self, yes: bool) -> Self {
+        self.quoting = yes;
+        self
+    }
+
+    /// Sets the escape character used for quotes, e.g. `Some(b'\\')`. The default is `None`.
+    ///
+    /// An escape character is only honored when [`double_quote`](Self::double_quote) is disabled.
+    pub fn escape(mut self, escape: Option<u8>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Enables or disables treating two consecutive quotes as an escaped quote. The default is `true`.
+    pub fn double_quote(mut self, yes: bool) -> Self {
+        self.double_quote = yes;
+        self
+    }
+
+    /// Sets the comment character. Lines starting with it are skipped. The default is `None`.
+    pub fn comment(mut self, comment: Option<u8>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Sets whether the first row is a header row. The default is `true`.
+    ///
+    /// Header rows are never searched, counted or merged, and provide the column names reported in matches.
+    pub fn has_headers(mut self, yes: bool) -> Self {
+        self.has_headers = yes;
+        self
+    }
+
+    /// Allows records with a varying number of fields. The default is `false`.
+    pub fn flexible(mut self, yes: bool) -> Self {
+        self.flexible = yes;
+        self
+    }
+
+    /// Sets which whitespace is trimmed from headers and fields. The default is [`Trim::None`].
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Sets the record terminator. The default is [`Terminator::CRLF`], which accepts `\r`, `\n` and `\r\n` when
+    /// reading and writes `\n`.
+    pub fn terminator(mut self, terminator: Terminator) -> Self {
+        self.terminator = terminator;
+        self
+    }
+
+    /// Restricts matching to the given column, in addition to any column already selected.
+    ///
+    /// When no column is selected, every column is searched.
+    pub fn column<C: Into<ColumnSelector>>(mut self, column: C) -> Self {
+        self.columns.push(column.into());
+        self
+    }
+
+    /// Restricts matching to the given columns, in addition to any column already selected.
+    pub fn columns<I, C>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<ColumnSelector>,
+    {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Excludes the given column from matching, even if it is selected.
+    pub fn exclude_column<C: Into<ColumnSelector>>(mut self, column: C) -> Self {
+        self.exclude_columns.push(column.into());
+        self
+    }
+
+    /// Excludes the given columns from matching, even if they are selected.
+    pub fn exclude_columns<I, C>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<ColumnSelector>,
+    {
+        self.exclude_columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets how much of a field the pattern has to match. The default is [`MatchMode::Substring`].
+    pub fn match_mode(mut self, mode: MatchMode) -> Self {
+        self.match_mode = mode;
+        self
+    }
+
+    /// Sets what the count functions count. The default is [`CountMode::Fields`].
+    pub fn count_mode(mut self, mode: CountMode) -> Self {
+        self.count_mode = mode;
+        self
+    }
+
+    /// Sets how many of the most frequent matched values a [`CountReport`](crate::CountReport) lists. The default is
+    /// `0`, which skips tracking distinct values; any other value keeps one counter per distinct matched text.
+    pub fn top_values(mut self, n: usize) -> Self {
+        self.top_values = n;
+        self
+    }
+
+    /// Inverts matching, like grep's `-v`: the search and count functions report the selected fields the pattern
+    /// does *not* match. The default is `false`.
+    ///
+    /// An inverted match spans the whole field, and a record matches when any of its selected fields does. Functions
+    /// taking a [`PatternSet`](crate::PatternSet) or [`ColumnRules`](crate::ColumnRules) report the patterns or rules
+    /// a field does not match. Merges are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv_coincidence::{find_matches_in, SearchOptions};
+    ///
+    /// let data = "name,phone\nJhon,555-1234\nMarta,n/a\nLuis,5551234\n";
+    /// let options = SearchOptions::new().column("phone").invert(true);
+    /// let failing = find_matches_in(data.as_bytes(), r"^\d{3}-\d{4}$", &options).unwrap();
+    ///
+    /// let records: Vec<u64> = failing.iter().map(|m| m.record).collect();
+    /// assert_eq!(records, vec![2, 3]);
+    /// assert_eq!((failing[0].value.as_str(), failing[0].start, failing[0].end), ("n/a", 0, 3));
+    /// ```
+    pub fn invert(mut self, yes: bool) -> Self {
+        self.invert = yes;
+        self
+    }
+
+    /// Restricts every function to the records satisfying the [`Query`], in addition to any filter already set.
+    ///
+    /// Records filtered out are neither searched nor counted, and merges copy them unchanged. Filters are applied
+    /// before [`invert`](Self::invert), which only inverts the pattern.
+    ///
+    /// The functions comparing records by key (joins, cross coincidences, linkage, duplicates and diffs) ignore the
+    /// records filtered out of either input without renumbering the others, and deduplication never drops them. The
+    /// columns the query names must exist in every input.
+    pub fn filter(mut self, query: Query) -> Self {
+        self.filters.push(query);
+        self
+    }
+
+    /// Restricts the search, count and merge functions to the records satisfying every one of the queries, in
+    /// addition to any filter already set.
+    pub fn filters<I: IntoIterator<Item = Query>>(mut self, queries: I) -> Self {
+        self.filters.extend(queries);
+        self
+    }
+
+    pub(crate) fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .quoting(self.quoting)
+            .escape(self.escape)
+            .double_quote(self.double_quote)
+            .comment(self.comment)
+            .has_headers(self.has_headers)
+            .flexible(self.flexible)
+            .trim(self.trim)
+            .terminator(self.terminator);
+        builder
+    }
+
+    pub(crate) fn writer_builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .double_quote(self.double_quote)
+            .escape(self.escape.unwrap_or(b'\\'))
+            .flexible(self.flexible)
+            .terminator(match self.terminator {
+                Terminator::CRLF => Terminator::Any(b'\n'),
+                terminator => terminator,
+            });
+        builder
+    }
+
+    pub(crate) fn matcher(&self, pattern: &Pattern) -> Result<Matcher, CoincidenceError> {
+        let matcher = pattern.compile(self.match_mode)?;
+        match self.invert {
+            true => Ok(Matcher::Inverted(Box::new(matcher))),
+            false => Ok(matcher),
+        }
+    }
+
+    pub(crate) fn regex(&self, pattern: &Pattern) -> Result<Regex, CoincidenceError> {
+        pattern.compile_regex(self.match_mode)
+    }
+
+    pub(crate) fn read_headers<R: Read>(&self, rdr: &mut Reader<R>) -> Result<Option<StringRecord>, CoincidenceError> {
+        if self.has_headers {
+            Ok(Some(rdr.headers()?.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn column_filter(&self, headers: Option<&StringRecord>) -> Result<ColumnFilter, CoincidenceError> {
+        ColumnFilter::resolve(&self.columns, &self.exclude_columns, headers)
+    }
+
+    pub(crate) fn record_filter(&self, headers: Option<&StringRecord>) -> Result<RecordFilter, CoincidenceError> {
+        RecordFilter::resolve(&self.filters, headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_builder_applies_options() {
+        let data = "# exported\n'Jhon';'madrid'\n'Marta';'paris'\n";
+        let options = SearchOptions::new()
+            .delimiter(b';')
+            .quote(b'\'')
+            .comment(Some(b'#'))
+            .has_headers(false);
+
+        let mut rdr = options.reader_builder().from_reader(data.as_bytes());
+        let records: Vec<Vec<String>> = rdr
+            .records()
+            .map(|r| r.unwrap().iter().map(str::to_string).collect())
+            .collect();
+
+        assert_eq!(records, vec![vec!["Jhon", "madrid"], vec!["Marta", "paris"]]);
+    }
+
+    #[test]
+    fn test_writer_builder_uses_delimiter() {
+        let options = SearchOptions::new().delimiter(b'\t');
+        let mut wtr = options.writer_builder().from_writer(vec![]);
+        wtr.write_record(["a", "b"]).unwrap();
+
+        assert_eq!(wtr.into_inner().unwrap(), b"a\tb\n");
+    }
+}
@@ -0,0 +1,352 @@
+use crate::CoincidenceError;
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// How much of a field a pattern has to match for the field to count as a coincidence.
+///
+/// Every mode other than `Substring` anchors the pattern as a whole: the pattern is validated on its own and then
+/// wrapped in a non-capturing group, so alternations such as `cat|dog` are anchored on both branches and capture
+/// group numbers are unchanged.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_coincidences_in, MatchMode, SearchOptions};
+///
+/// let data = "code\nabc\nxabcx\nabcd\n";
+///
+/// let options = SearchOptions::new().match_mode(MatchMode::Substring);
+/// assert_eq!(count_coincidences_in(data.as_bytes(), "abc", &options).unwrap(), 3);
+///
+/// let options = SearchOptions::new().match_mode(MatchMode::FullField);
+/// assert_eq!(count_coincidences_in(data.as_bytes(), "abc", &options).unwrap(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// The pattern may match anywhere in the field.
+    #[default]
+    Substring,
+    /// The pattern must match the entire field.
+    FullField,
+    /// The pattern must match at the start of the field.
+    StartsWith,
+    /// The pattern must match at the end of the field.
+    EndsWith,
+    /// The pattern must match whole words, delimited by word boundaries.
+    WordBoundary,
+}
+
+impl MatchMode {
+    /// Returns the given (already validated) pattern anchored according to this mode, or `None` for `Substring`.
+    fn anchor(self, pattern: &str) -> Option<String> {
+        match self {
+            MatchMode::Substring => None,
+            MatchMode::FullField => Some(format!(r"\A(?:{})\z", pattern)),
+            MatchMode::StartsWith => Some(format!(r"\A(?:{})", pattern)),
+            MatchMode::EndsWith => Some(format!(r"(?:{})\z", pattern)),
+            MatchMode::WordBoundary => Some(format!(r"\b(?:{})\b", pattern)),
+        }
+    }
+
+    /// Returns the given (already validated) pattern source anchored according to this mode, to be compiled with
+    /// the same settings as the unanchored pattern.
+    pub(crate) fn anchored(self, pattern: &str) -> Cow<'_, str> {
+        match self.anchor(pattern) {
+            Some(anchored) => Cow::Owned(anchored),
+            None => Cow::Borrowed(pattern),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PatternKind {
+    Regex(String),
+    Literals(Vec<String>),
+}
+
+/// What to search for: a regular expression or plain literal text, with matching flags.
+///
+/// Every function taking a pattern accepts a `&str` or `String`, which is treated as a regular expression with the
+/// default flags, or a `Pattern` for literal text and custom flags.
+///
+/// Literal patterns need no escaping: `Pattern::literal("A.1+(b)")` matches that exact text. Searches for several
+/// literals with [`Pattern::literals`] use Aho-Corasick when matching substrings.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_partial_matches_in, Pattern, SearchOptions};
+///
+/// let data = "code\nAB.1+(x)\nab.1+(X)\nAB21+x\n";
+/// let pattern = Pattern::literal("ab.1+(x)").case_insensitive(true);
+///
+/// let matches = find_partial_matches_in(data.as_bytes(), pattern, &SearchOptions::default()).unwrap();
+/// assert_eq!(matches, vec!["AB.1+(x)", "ab.1+(X)"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    kind: PatternKind,
+    case_insensitive: bool,
+    unicode: bool,
+    multi_line: bool,
+    dot_matches_new_line: bool,
+}
+
+impl Pattern {
+    fn new(kind: PatternKind) -> Self {
+        Pattern {
+            kind,
+            case_insensitive: false,
+            unicode: true,
+            multi_line: false,
+            dot_matches_new_line: false,
+        }
+    }
+
+    /// Creates a pattern from a regular expression.
+    pub fn regex<S: Into<String>>(pattern: S) -> Self {
+        Self::new(PatternKind::Regex(pattern.into()))
+    }
+
+    /// Creates a pattern matching the given text literally.
+    pub fn literal<S: Into<String>>(text: S) -> Self {
+        Self::new(PatternKind::Literals(vec![text.into()]))
+    }
+
+    /// Creates a pattern matching any of the given texts literally. When several terms match at the same position,
+    /// the one listed first wins.
+    pub fn literals<I, S>(terms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::new(PatternKind::Literals(terms.into_iter().map(Into::into).collect()))
+    }
+
+    /// Enables or disables case-insensitive matching. The default is `false`.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Enables or disables Unicode support. The default is `true`.
+    ///
+    /// With Unicode enabled, case-insensitive matching uses Unicode case folding (so `ß` matches `ẞ`) and classes
+    /// such as `\w` cover all scripts. When disabled, both are restricted to ASCII; constructs that could match
+    /// invalid UTF-8, such as `.`, are then rejected.
+    pub fn unicode(mut self, yes: bool) -> Self {
+        self.unicode = yes;
+        self
+    }
+
+    /// Enables or disables multi-line mode, in which `^` and `$` match at line boundaries within a field. The default
+    /// is `false`.
+    pub fn multi_line(mut self, yes: bool) -> Self {
+        self.multi_line = yes;
+        self
+    }
+
+    /// Enables or disables letting `.` match `\n`. The default is `false`.
+    pub fn dot_matches_new_line(mut self, yes: bool) -> Self {
+        self.dot_matches_new_line = yes;
+        self
+    }
+
+    fn source(&self) -> Result<Cow<'_, str>, CoincidenceError> {
+        match &self.kind {
+            PatternKind::Regex(pattern) => Ok(Cow::Borrowed(pattern)),
+            PatternKind::Literals(terms) if terms.is_empty() => Err(CoincidenceError::EmptyPattern),
+            PatternKind::Literals(terms) => {
+                Ok(Cow::Owned(terms.iter().map(|term| regex::escape(term)).collect::<Vec<_>>().join("|")))
+            }
+        }
+    }
+
+    fn build(&self, pattern: &str) -> Result<Regex, CoincidenceError> {
+        Ok(RegexBuilder::new(pattern)
+            .case_insensitive(self.case_insensitive)
+            .unicode(self.unicode)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .build()?)
+    }
+
+    /// Compiles the pattern into a `Regex`, anchored according to the given mode.
+    pub(crate) fn compile_regex(&self, mode: MatchMode) -> Result<Regex, CoincidenceError> {
+        let source = self.source()?;
+        let re = self.build(&source)?;
+
+        match mode.anchor(&source) {
+            Some(anchored) => self.build(&anchored),
+            None => Ok(re),
+        }
+    }
+
+    /// Compiles the pattern into the fastest matcher available for the given mode.
+    pub(crate) fn compile(&self, mode: MatchMode) -> Result<Matcher, CoincidenceError> {
+        if let PatternKind::Literals(terms) = &self.kind {
+            let ascii_folding = !self.case_insensitive || terms.iter().all(|term| term.is_ascii());
+
+            if terms.len() > 1 && mode == MatchMode::Substring && ascii_folding && self.source().is_ok() {
+                let automaton = AhoCorasick::builder()
+                    .match_kind(MatchKind::LeftmostFirst)
+                    .ascii_case_insensitive(self.case_insensitive)
+                    .build(terms);
+
+                if let Ok(automaton) = automaton {
+                    return Ok(Matcher::Literals(automaton));
+                }
+            }
+        }
+
+        Ok(Matcher::Regex(self.compile_regex(mode)?))
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(pattern: &str) -> Self {
+        Pattern::regex(pattern)
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(pattern: String) -> Self {
+        Pattern::regex(pattern)
+    }
+}
+
+impl From<&String> for Pattern {
+    fn from(pattern: &String) -> Self {
+        Pattern::regex(pattern.as_str())
+    }
+}
+
+impl From<&Pattern> for Pattern {
+    fn from(pattern: &Pattern) -> Self {
+        pattern.clone()
+    }
+}
+
+/// A compiled [`Pattern`].
+#[derive(Debug, Clone)]
+pub(crate) enum Matcher {
+    Regex(Regex),
+    Literals(AhoCorasick),
+    /// Matches the whole of every field the inner matcher does not match.
+    Inverted(Box<Matcher>),
+}
+
+impl Matcher {
+    /// Returns the span of the first match in the field.
+    pub(crate) fn find(&self, field: &str) -> Option<Range<usize>> {
+        match self {
+            Matcher::Regex(re) => re.find(field).map(|m| m.range()),
+            Matcher::Literals(automaton) => automaton.find(field).map(|m| m.range()),
+            Matcher::Inverted(matcher) => match matcher.find(field) {
+                Some(_) => None,
+                None => Some(0..field.len()),
+            },
+        }
+    }
+
+    /// Returns the spans of every non-overlapping match in the field.
+    pub(crate) fn find_iter<'a>(&'a self, field: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        match self {
+            Matcher::Regex(re) => Box::new(re.find_iter(field).map(|m| m.range())),
+            Matcher::Literals(automaton) => Box::new(automaton.find_iter(field).map(|m| m.range())),
+            Matcher::Inverted(_) => Box::new(self.find(field).into_iter()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes_anchor_alternations() {
+        let matching = |mode: MatchMode, field: &str| {
+            let re = Pattern::regex("cat|dog").compile_regex(mode).unwrap();
+            re.is_match(field)
+        };
+
+        assert!(matching(MatchMode::Substring, "hotdogs"));
+        assert!(matching(MatchMode::FullField, "dog"));
+        assert!(!matching(MatchMode::FullField, "cat dog"));
+        assert!(!matching(MatchMode::FullField, "catx"));
+        assert!(matching(MatchMode::StartsWith, "dogs"));
+        assert!(!matching(MatchMode::StartsWith, "hotdog"));
+        assert!(matching(MatchMode::EndsWith, "hotdog"));
+        assert!(!matching(MatchMode::EndsWith, "cats"));
+        assert!(matching(MatchMode::WordBoundary, "a cat here"));
+        assert!(!matching(MatchMode::WordBoundary, "concatenate"));
+    }
+
+    #[test]
+    fn test_full_field_prefers_the_whole_field() {
+        let re = Pattern::regex("a|ab").compile_regex(MatchMode::FullField).unwrap();
+        assert_eq!(re.find("ab").unwrap().range(), 0..2);
+    }
+
+    #[test]
+    fn test_unbalanced_patterns_are_rejected_before_wrapping() {
+        let result = Pattern::regex("a)|(b").compile_regex(MatchMode::FullField);
+        assert!(matches!(result, Err(CoincidenceError::Regex(_))));
+    }
+
+    #[test]
+    fn test_anchors_ignore_multi_line_flag() {
+        let re = Pattern::regex("^b$").multi_line(true).compile_regex(MatchMode::FullField).unwrap();
+        assert!(!re.is_match("a\nb"));
+    }
+
+    #[test]
+    fn test_anchoring_keeps_the_pattern_flags() {
+        let re = Pattern::regex("abc").case_insensitive(true).compile_regex(MatchMode::FullField).unwrap();
+        assert!(re.is_match("ABC"));
+        assert!(!re.is_match("ABCD"));
+
+        assert!(matches!(MatchMode::Substring.anchored("(?i)abc"), Cow::Borrowed("(?i)abc")));
+        let re = crate::compile_pattern(&MatchMode::StartsWith.anchored("(?i)abc")).unwrap();
+        assert!(re.is_match("ABCD"));
+        assert!(!re.is_match("xabc"));
+    }
+
+    #[test]
+    fn test_literals_need_no_escaping() {
+        let re = Pattern::literal("1.5+(x)").compile_regex(MatchMode::FullField).unwrap();
+        assert!(re.is_match("1.5+(x)"));
+        assert!(!re.is_match("105+(x)"));
+    }
+
+    #[test]
+    fn test_multiple_literals_use_aho_corasick() {
+        let pattern = Pattern::literals(["C++", "c#"]).case_insensitive(true);
+        let matcher = pattern.compile(MatchMode::Substring).unwrap();
+
+        assert!(matches!(matcher, Matcher::Literals(_)));
+        assert_eq!(matcher.find("we use C# and c++"), Some(7..9));
+        assert_eq!(matcher.find("rust"), None);
+        assert_eq!(matcher.find_iter("c#, C++ and c#").count(), 3);
+    }
+
+    #[test]
+    fn test_unicode_case_folding() {
+        let pattern = Pattern::literals(["straße", "größe"]).case_insensitive(true);
+        let matcher = pattern.compile(MatchMode::Substring).unwrap();
+
+        assert!(matches!(matcher, Matcher::Regex(_)));
+        assert_eq!(matcher.find("STRASSE STRAẞE"), Some(8..16));
+    }
+
+    #[test]
+    fn test_literals_without_terms_are_rejected() {
+        let result = Pattern::literals(Vec::<String>::new()).compile(MatchMode::Substring);
+        assert!(matches!(result, Err(CoincidenceError::EmptyPattern)));
+
+        let matcher = Pattern::literals(["a", ""]).compile(MatchMode::Substring).unwrap();
+        assert!(matcher.find("xyz").is_some());
+    }
+}
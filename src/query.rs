@@ -0,0 +1,663 @@
+use crate::columns::resolve_columns;
+use crate::{validate_csv_extension, CoincidenceError, ColumnSelector, ContextOptions, RecordMatches, SearchOptions};
+use csv::StringRecord;
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::Read;
+use std::slice;
+use std::str::FromStr;
+
+/// A boolean query over the columns of a record, such as
+/// `name ~ /^J/ AND (city = "Madrid" OR age > 30) AND NOT email ~ /@test\./`.
+///
+/// A query combines column predicates with `AND`, `OR`, `NOT` and parentheses. `NOT` binds tighter than `AND`, which
+/// binds tighter than `OR`, and the keywords are case-insensitive. Each predicate compares a column with a value:
+///
+/// * The column is a header name, a quoted header name (`"unit price"`) or a zero-based column index.
+/// * `~` and `!~` test whether the field matches a regular expression written as `/pattern/`, or `/pattern/i` to
+///   ignore case. A `/` inside the pattern is written `\/`; `\\` stays an escaped backslash, so `/a\\/` ends after it.
+/// * `=`, `!=`, `<`, `<=`, `>` and `>=` compare the field with a quoted string, byte by byte, or with a number, in
+///   which case fields that are not numbers only satisfy `!=`.
+///
+/// A column missing from a short record compares as an empty field. Queries are parsed once and evaluated per record
+/// in a single pass; use [`SearchOptions::filter`] to restrict every function to the records a query selects, or
+/// [`query_records`] to return the records themselves.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{count_coincidences_in, Query, SearchOptions};
+///
+/// let data = "name,city,age\nJhon,Madrid,25\nJane,Paris,41\nJoe,Paris,19\nMarta,Madrid,52\n";
+/// let query: Query = r#"name ~ /^J/ AND (city = "Madrid" OR age > 30)"#.parse().unwrap();
+/// let options = SearchOptions::new().filter(query).column("name");
+///
+/// assert_eq!(count_coincidences_in(data.as_bytes(), ".", &options).unwrap(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+}
+
+/// A node of the syntax tree of a [`Query`].
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// Both expressions hold.
+    And(Box<Expr>, Box<Expr>),
+    /// Either expression holds.
+    Or(Box<Expr>, Box<Expr>),
+    /// The expression does not hold.
+    Not(Box<Expr>),
+    /// A comparison of a column with a value.
+    Predicate(Predicate),
+}
+
+/// A comparison of a column with a value, the leaves of a [`Query`].
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    /// The compared column, a [`ColumnSelector::Name`] or a [`ColumnSelector::Index`].
+    pub column: ColumnSelector,
+    /// The comparison operator.
+    pub comparison: Comparison,
+    /// The value the field is compared with.
+    pub value: Value,
+}
+
+/// The operator of a [`Predicate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `~`: the field matches the regular expression.
+    Matches,
+    /// `!~`: the field does not match the regular expression.
+    NotMatches,
+    /// `=`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+}
+
+/// The value of a [`Predicate`].
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// A `/regular expression/`, only used with `~` and `!~`.
+    Regex(Regex),
+    /// A quoted string.
+    Text(String),
+    /// A number, which makes the comparison numeric.
+    Number(f64),
+}
+
+impl Query {
+    /// Parses a query.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Query` with the character offset of the offending token if the query is not valid,
+    /// including when one of its regular expressions is empty or invalid.
+    pub fn parse(source: &str) -> Result<Self, CoincidenceError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let expr = parser.or()?;
+
+        match parser.peek() {
+            (_, Token::End) => Ok(Query { expr }),
+            (position, token) => Err(query_error(*position, format!("unexpected {}", token.describe()))),
+        }
+    }
+
+    /// Returns the root of the syntax tree of the query.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+
+impl FromStr for Query {
+    type Err = CoincidenceError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Query::parse(source)
+    }
+}
+
+impl Predicate {
+    fn holds(&self, field: &str) -> bool {
+        match (&self.value, self.comparison) {
+            (Value::Regex(re), Comparison::NotMatches) => !re.is_match(field),
+            (Value::Regex(re), _) => re.is_match(field),
+            (Value::Text(text), comparison) => comparison.holds(field.cmp(text.as_str())),
+            (Value::Number(number), comparison) => match field.trim().parse::<f64>() {
+                Ok(field) => field.partial_cmp(number).is_some_and(|ordering| comparison.holds(ordering)),
+                Err(_) => comparison == Comparison::NotEqual,
+            },
+        }
+    }
+}
+
+impl Comparison {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Equal | Comparison::Matches => ordering == Ordering::Equal,
+            Comparison::NotEqual | Comparison::NotMatches => ordering != Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Returns the whole records of the CSV file selected by a query, together with the records surrounding them.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `query` - The [`Query`] the returned records satisfy.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read. Its own filters must hold as well.
+/// * `context` - The [`ContextOptions`] describing how many surrounding records are returned.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RecordMatches`] if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if a column of the query does not exist.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{query_records, ContextOptions, Query, SearchOptions};
+///
+/// let query = Query::parse(r#"status = "failed" AND retries >= 3"#).unwrap();
+/// let found = query_records("jobs.csv", &query, &SearchOptions::default(), &ContextOptions::new()).unwrap();
+/// ```
+pub fn query_records(
+    file_path: &str,
+    query: &Query,
+    options: &SearchOptions,
+    context: &ContextOptions,
+) -> Result<RecordMatches, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    query_records_in(file, query, options, context)
+}
+
+/// Returns the whole records of CSV data read from any `std::io::Read` source selected by a query, together with the
+/// records surrounding them.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `query` - The [`Query`] the returned records satisfy.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read. Its own filters must hold as well.
+/// * `context` - The [`ContextOptions`] describing how many surrounding records are returned.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RecordMatches`] if successful, or an error if there is any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, or if a column of the
+/// query does not exist.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{query_records_in, ContextOptions, Query, SearchOptions};
+///
+/// let data = "name,email\nJhon,jhon@test.com\nMarta,marta@example.com\n";
+/// let query = Query::parse(r"NOT email ~ /@test\./").unwrap();
+/// let found = query_records_in(data.as_bytes(), &query, &SearchOptions::default(), &ContextOptions::new()).unwrap();
+///
+/// assert_eq!(found.records.len(), 1);
+/// assert_eq!(found.records[0].fields, vec!["Marta", "marta@example.com"]);
+/// ```
+pub fn query_records_in<R: Read>(
+    reader: R,
+    query: &Query,
+    options: &SearchOptions,
+    context: &ContextOptions,
+) -> Result<RecordMatches, CoincidenceError> {
+    let options = options.clone().filter(query.clone());
+    crate::context::context_records(reader, None, &options, context)
+}
+
+/// The queries of `SearchOptions` with their columns resolved against the header row.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RecordFilter {
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Predicate(usize, Predicate),
+}
+
+impl RecordFilter {
+    pub(crate) fn resolve(queries: &[Query], headers: Option<&StringRecord>) -> Result<Self, CoincidenceError> {
+        let filters = queries
+            .iter()
+            .map(|query| Filter::resolve(&query.expr, headers))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RecordFilter { filters })
+    }
+
+    /// Returns whether the record satisfies every query.
+    pub(crate) fn is_selected(&self, record: &StringRecord) -> bool {
+        self.filters.iter().all(|filter| filter.holds(record))
+    }
+}
+
+impl Filter {
+    fn resolve(expr: &Expr, headers: Option<&StringRecord>) -> Result<Self, CoincidenceError> {
+        let both = |left: &Expr, right: &Expr| -> Result<_, CoincidenceError> {
+            Ok((Box::new(Filter::resolve(left, headers)?), Box::new(Filter::resolve(right, headers)?)))
+        };
+
+        Ok(match expr {
+            Expr::And(left, right) => {
+                let (left, right) = both(left, right)?;
+                Filter::And(left, right)
+            }
+            Expr::Or(left, right) => {
+                let (left, right) = both(left, right)?;
+                Filter::Or(left, right)
+            }
+            Expr::Not(expr) => Filter::Not(Box::new(Filter::resolve(expr, headers)?)),
+            Expr::Predicate(predicate) => {
+                let columns = resolve_columns(slice::from_ref(&predicate.column), headers)?;
+                Filter::Predicate(columns[0], predicate.clone())
+            }
+        })
+    }
+
+    fn holds(&self, record: &StringRecord) -> bool {
+        match self {
+            Filter::And(left, right) => left.holds(record) && right.holds(record),
+            Filter::Or(left, right) => left.holds(record) || right.holds(record),
+            Filter::Not(filter) => !filter.holds(record),
+            Filter::Predicate(column, predicate) => predicate.holds(record.get(*column).unwrap_or("")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Text(String),
+    Number(String),
+    Regex(String, bool),
+    Comparison(Comparison),
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("`{}`", name),
+            Token::Text(text) => format!("string \"{}\"", text),
+            Token::Number(number) => format!("number {}", number),
+            Token::Regex(pattern, _) => format!("regular expression /{}/", pattern),
+            Token::Comparison(_) => "comparison operator".to_string(),
+            Token::Open => "`(`".to_string(),
+            Token::Close => "`)`".to_string(),
+            Token::And => "`AND`".to_string(),
+            Token::Or => "`OR`".to_string(),
+            Token::Not => "`NOT`".to_string(),
+            Token::End => "end of query".to_string(),
+        }
+    }
+}
+
+fn query_error(position: usize, message: impl Into<String>) -> CoincidenceError {
+    CoincidenceError::Query {
+        position,
+        message: message.into(),
+    }
+}
+
+/// Splits the query into tokens, each with the character offset where it starts.
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, CoincidenceError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let next = chars.get(i + 1).copied();
+
+        let token = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '~' => Token::Comparison(Comparison::Matches),
+            '!' | '=' | '<' | '>' => {
+                let comparison = match (chars[i], next) {
+                    ('!', Some('~')) => Comparison::NotMatches,
+                    ('!', Some('=')) => Comparison::NotEqual,
+                    ('=', _) => Comparison::Equal,
+                    ('<', Some('=')) => Comparison::LessOrEqual,
+                    ('<', _) => Comparison::Less,
+                    ('>', Some('=')) => Comparison::GreaterOrEqual,
+                    ('>', _) => Comparison::Greater,
+                    (c, _) => return Err(query_error(start, format!("unexpected character `{}`", c))),
+                };
+                if next == Some('=') || comparison == Comparison::NotMatches {
+                    i += 1;
+                }
+                Token::Comparison(comparison)
+            }
+            quote @ ('"' | '\'') => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(query_error(start, "unterminated string")),
+                        Some(&c) if c == quote => break,
+                        Some('\\') => {
+                            i += 1;
+                            text.push(match chars.get(i) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some(&c @ ('\\' | '"' | '\'')) => c,
+                                Some(_) => return Err(query_error(i, "unknown escape sequence")),
+                                None => return Err(query_error(start, "unterminated string")),
+                            });
+                        }
+                        Some(&c) => text.push(c),
+                    }
+                    i += 1;
+                }
+                Token::Text(text)
+            }
+            '/' => {
+                let mut pattern = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(query_error(start, "unterminated regular expression")),
+                        Some('/') => break,
+                        Some('\\') if chars.get(i + 1) == Some(&'\\') => {
+                            pattern.push_str("\\\\");
+                            i += 1;
+                        }
+                        Some('\\') if chars.get(i + 1) == Some(&'/') => {
+                            pattern.push('/');
+                            i += 1;
+                        }
+                        Some(&c) => pattern.push(c),
+                    }
+                    i += 1;
+                }
+                let mut case_insensitive = false;
+                while let Some(&flag) = chars.get(i + 1).filter(|c| c.is_alphanumeric()) {
+                    match flag {
+                        'i' => case_insensitive = true,
+                        _ => return Err(query_error(i + 1, format!("unknown regular expression flag `{}`", flag))),
+                    }
+                    i += 1;
+                }
+                Token::Regex(pattern, case_insensitive)
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                while chars.get(i + 1).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                    i += 1;
+                }
+                Token::Number(chars[start..=i].iter().collect())
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while chars.get(i + 1).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..=i].iter().collect();
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Ident(word),
+                }
+            }
+            c => return Err(query_error(start, format!("unexpected character `{}`", c))),
+        };
+
+        tokens.push((start, token));
+        i += 1;
+    }
+
+    tokens.push((chars.len(), Token::End));
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the tokens of a query.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &(usize, Token) {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> (usize, Token) {
+        let token = self.tokens[self.next].clone();
+        if token.1 != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, CoincidenceError> {
+        let mut expr = self.and()?;
+        while self.peek().1 == Token::Or {
+            self.advance();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, CoincidenceError> {
+        let mut expr = self.not()?;
+        while self.peek().1 == Token::And {
+            self.advance();
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, CoincidenceError> {
+        if self.peek().1 == Token::Not {
+            self.advance();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CoincidenceError> {
+        if self.peek().1 == Token::Open {
+            self.advance();
+            let expr = self.or()?;
+            return match self.advance() {
+                (_, Token::Close) => Ok(expr),
+                (position, token) => Err(query_error(position, format!("expected `)`, found {}", token.describe()))),
+            };
+        }
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<Expr, CoincidenceError> {
+        let column = match self.advance() {
+            (_, Token::Ident(name) | Token::Text(name)) => ColumnSelector::Name(name),
+            (position, Token::Number(number)) => ColumnSelector::Index(
+                number
+                    .parse()
+                    .map_err(|_| query_error(position, format!("invalid column index {}", number)))?,
+            ),
+            (position, token) => {
+                return Err(query_error(position, format!("expected a column, found {}", token.describe())))
+            }
+        };
+
+        let comparison = match self.advance() {
+            (_, Token::Comparison(comparison)) => comparison,
+            (position, token) => {
+                let message = format!("expected a comparison operator such as `=` or `~`, found {}", token.describe());
+                return Err(query_error(position, message));
+            }
+        };
+
+        let regex_comparison = matches!(comparison, Comparison::Matches | Comparison::NotMatches);
+        let value = match (self.advance(), regex_comparison) {
+            ((position, Token::Regex(pattern, case_insensitive)), true) => {
+                if pattern.is_empty() {
+                    return Err(query_error(position, "the regular expression is empty"));
+                }
+                let re = RegexBuilder::new(&pattern)
+                    .case_insensitive(case_insensitive)
+                    .build()
+                    .map_err(|err| query_error(position, format!("invalid regular expression: {}", err)))?;
+                Value::Regex(re)
+            }
+            ((_, Token::Text(text)), false) => Value::Text(text),
+            ((position, Token::Number(number)), false) => Value::Number(
+                number
+                    .parse()
+                    .map_err(|_| query_error(position, format!("invalid number {}", number)))?,
+            ),
+            ((position, token), true) => {
+                let message = format!("expected a /regular expression/, found {}", token.describe());
+                return Err(query_error(position, message));
+            }
+            ((position, token), false) => {
+                let message = format!("expected a quoted string or a number, found {}", token.describe());
+                return Err(query_error(position, message));
+            }
+        };
+
+        Ok(Expr::Predicate(Predicate {
+            column,
+            comparison,
+            value,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MergeMode, MergeOptions};
+
+    const PEOPLE: &str = "name,city,age,email\n\
+        Jhon,Madrid,25,jhon@example.com\n\
+        Jane,Paris,41,jane@test.com\n\
+        Joe,Paris,35,joe@example.com\n\
+        Marta,Madrid,52,marta@example.com\n\
+        Juan,Lima,n/a,juan@example.com\n";
+
+    fn selected(query: &str) -> Vec<u64> {
+        let query = Query::parse(query).unwrap();
+        let found = query_records_in(PEOPLE.as_bytes(), &query, &SearchOptions::default(), &ContextOptions::new());
+        found.unwrap().records.iter().map(|r| r.record).collect()
+    }
+
+    fn error(query: &str) -> (usize, String) {
+        match Query::parse(query).unwrap_err() {
+            CoincidenceError::Query { position, message } => (position, message),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_evaluates_the_example_query() {
+        assert_eq!(
+            selected(r#"name ~ /^J/ AND (city = "Madrid" OR age > 30) AND NOT email ~ /@test\./"#),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_keywords() {
+        assert_eq!(selected(r#"city = "Lima" or city = "Madrid" and age < 30"#), vec![1, 5]);
+        assert_eq!(selected(r#"NOT NOT city == 'Paris'"#), vec![2, 3]);
+        assert_eq!(selected(r"name ~ /^j/i AND 2 >= 35"), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_numbers_and_missing_values() {
+        assert_eq!(selected("age != 25"), vec![2, 3, 4, 5]);
+        assert_eq!(selected("age <= 35"), vec![1, 3]);
+        assert_eq!(selected(r#"age < "3""#), vec![1]);
+        assert_eq!(selected(r"email !~ /example/"), vec![2]);
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_the_offending_character() {
+        assert_eq!(error(r#"name = "Jhon" AND"#), (17, "expected a column, found end of query".to_string()));
+        assert_eq!(error(r#"(city = "Lima""#).0, 14);
+        assert_eq!(error(r#"name = "Jhon" & age > 3"#), (14, "unexpected character `&`".to_string()));
+        assert_eq!(error("name ~ \"J\"").0, 7);
+        assert_eq!(error("age > /3/").0, 6);
+        assert_eq!(error("name ~ /(/").0, 7);
+        assert_eq!(error("name ~ /J/x"), (10, "unknown regular expression flag `x`".to_string()));
+        assert_eq!(error("city = 'Lima").0, 7);
+        assert_eq!(error("city 'Lima'").0, 5);
+        assert_eq!(error("city = 'Lima' age").1, "unexpected `age`");
+    }
+
+    #[test]
+    fn test_regex_escapes() {
+        let regex = |source: &str| match tokenize(source).unwrap().into_iter().nth(2) {
+            Some((_, Token::Regex(pattern, _))) => pattern,
+            other => panic!("unexpected token {:?}", other),
+        };
+
+        assert_eq!(regex(r"path ~ /a\/b/"), "a/b");
+        assert_eq!(regex(r"path ~ /a\\/"), r"a\\");
+        assert_eq!(regex(r"path ~ /a\\\/b/"), r"a\\/b");
+        assert_eq!(regex(r"path ~ /\d+/"), r"\d+");
+    }
+
+    #[test]
+    fn test_filtered_records_are_copied_unchanged_by_merges() {
+        let options = SearchOptions::new().column("email").filter(Query::parse("age >= 50").unwrap());
+        let merge = MergeOptions::new().mode(MergeMode::FullFile);
+        let mut output = Vec::new();
+        crate::merge_coincidence_in(PEOPLE.as_bytes(), &mut output, "@.*", &options, &merge).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Jhon,Madrid,25,jhon@example.com\n"));
+        assert!(output.contains("Marta,Madrid,52,[MERGED]\n"));
+    }
+
+    #[test]
+    fn test_unknown_columns_are_reported_when_resolved() {
+        let query = Query::parse("country = 'ES'").unwrap();
+        let result = query_records_in(PEOPLE.as_bytes(), &query, &SearchOptions::default(), &ContextOptions::new());
+
+        assert!(matches!(result, Err(CoincidenceError::UnknownColumn(column)) if column == "country"));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::columns::{ColumnFilter, ColumnSelector};
+use crate::{compile_pattern, validate_csv_extension, CoincidenceError, Match, SearchOptions};
+use std::fs::File;
+use std::io::Read;
+use std::slice;
+
+/// How the rules of a [`ColumnRules`] set combine to decide whether a record is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleMode {
+    /// A record is reported when at least one rule matches.
+    #[default]
+    Any,
+    /// A record is reported only when every rule matches at least one of its fields.
+    All,
+}
+
+/// A set of per-column patterns evaluated together in a single pass over the CSV data.
+///
+/// Every pattern is anchored according to the [`MatchMode`](crate::MatchMode) of the [`SearchOptions`] it is applied
+/// with.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{find_rule_matches_in, ColumnRules, RuleMode, SearchOptions};
+///
+/// let data = "email,zip\njhon@example.com,28001\nmarta@example,2800\n";
+/// let rules = ColumnRules::new()
+///     .rule("email", r"^[^@]+@[^@]+\.[a-z]+$").unwrap()
+///     .rule("zip", r"^\d{5}$").unwrap()
+///     .mode(RuleMode::All);
+///
+/// let matches = find_rule_matches_in(data.as_bytes(), &rules, &SearchOptions::default()).unwrap();
+///
+/// assert_eq!(matches.len(), 2);
+/// assert!(matches.iter().all(|m| m.found.record == 1));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ColumnRules {
+    rules: Vec<(ColumnSelector, String)>,
+    mode: RuleMode,
+}
+
+/// A match produced by one of the rules of a [`ColumnRules`] set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleMatch {
+    /// The zero-based index of the rule that fired, in the order the rules were added.
+    pub rule: usize,
+    /// The location and value of the match.
+    pub found: Match,
+}
+
+impl ColumnRules {
+    /// Creates an empty rule set using [`RuleMode::Any`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule applying the given regular expression pattern to the selected column(s).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CoincidenceError::Regex` if the pattern is invalid.
+    pub fn rule<C: Into<ColumnSelector>>(mut self, column: C, pattern: &str) -> Result<Self, CoincidenceError> {
+        compile_pattern(pattern)?;
+        self.rules.push((column.into(), pattern.to_string()));
+        Ok(self)
+    }
+
+    /// Sets how the rules combine to decide whether a record is reported. The default is [`RuleMode::Any`].
+    pub fn mode(mut self, mode: RuleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the number of rules in the set.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns `true` if the set contains no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Applies every rule of a [`ColumnRules`] set to the CSV file in a single pass.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `rules` - The per-column patterns to apply.
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RuleMatch`] values of every reported record, in file order, or an error if there is
+/// any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be read, contains malformed records,
+/// or if a rule selects a column that does not exist.
+pub fn find_rule_matches(
+    file_path: &str,
+    rules: &ColumnRules,
+    options: &SearchOptions,
+) -> Result<Vec<RuleMatch>, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let file = File::open(file_path)?;
+    find_rule_matches_in(file, rules, options)
+}
+
+/// Applies every rule of a [`ColumnRules`] set in a single pass over CSV data read from any `std::io::Read` source.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `rules` - The per-column patterns to apply.
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RuleMatch`] values of every reported record, in input order, or an error if there is
+/// any issue during processing.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the data cannot be read, contains malformed records, or if a rule selects a
+/// column that does not exist.
+pub fn find_rule_matches_in<R: Read>(
+    reader: R,
+    rules: &ColumnRules,
+    options: &SearchOptions,
+) -> Result<Vec<RuleMatch>, CoincidenceError> {
+    let regexes = rules
+        .rules
+        .iter()
+        .map(|(_, pattern)| compile_pattern(&options.match_mode.anchored(pattern)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut rdr = options.reader_builder().from_reader(reader);
+    let headers = options.read_headers(&mut rdr)?;
+    let filter = options.column_filter(headers.as_ref())?;
+    let record_filter = options.record_filter(headers.as_ref())?;
+    let rule_columns = rules
+        .rules
+        .iter()
+        .map(|(column, _)| ColumnFilter::resolve(slice::from_ref(column), &[], headers.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut matches = Vec::new();
+    let mut fired = vec![false; rules.len()];
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = result?;
+        if !record_filter.is_selected(&record) {
+            continue;
+        }
+
+        let mut record_matches = Vec::new();
+        fired.iter_mut().for_each(|f| *f = false);
+
+        for (column, field) in record.iter().enumerate() {
+            if !filter.is_selected(column) {
+                continue;
+            }
+
+            for (rule, re) in regexes.iter().enumerate() {
+                if !rule_columns[rule].is_selected(column) {
+                    continue;
+                }
+
+                let found = match (re.find(field), options.invert) {
+                    (Some(found), false) => Some(found.range()),
+                    (None, true) => Some(0..field.len()),
+                    _ => None,
+                };
+
+                if let Some(span) = found {
+                    fired[rule] = true;
+                    record_matches.push(RuleMatch {
+                        rule,
+                        found: Match::new(index as u64 + 1, &record, column, headers.as_ref(), span),
+                    });
+                }
+            }
+        }
+
+        let reported = match rules.mode {
+            RuleMode::Any => true,
+            RuleMode::All => fired.iter().all(|&f| f),
+        };
+
+        if reported {
+            matches.append(&mut record_matches);
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatchMode;
+
+    const SIGNUPS: &str = "name,email,zip\n\
+        Jhon,jhon@example.com,28001\n\
+        Marta,marta-example.com,08001\n\
+        luis,luis@example.com,123\n";
+
+    fn rules() -> ColumnRules {
+        ColumnRules::new()
+            .rule("email", r"^[^@]+@[^@]+$")
+            .unwrap()
+            .rule("zip", r"^\d{5}$")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_any_mode_reports_each_rule() {
+        let matches = find_rule_matches_in(SIGNUPS.as_bytes(), &rules(), &SearchOptions::default()).unwrap();
+        let fired: Vec<(u64, usize)> = matches.iter().map(|m| (m.found.record, m.rule)).collect();
+
+        assert_eq!(fired, vec![(1, 0), (1, 1), (2, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn test_inverted_rules_report_failing_fields() {
+        let options = SearchOptions::new().invert(true);
+        let matches = find_rule_matches_in(SIGNUPS.as_bytes(), &rules(), &options).unwrap();
+        let failed: Vec<(u64, &str)> = matches.iter().map(|m| (m.found.record, m.found.value.as_str())).collect();
+
+        assert_eq!(failed, vec![(2, "marta-example.com"), (3, "123")]);
+    }
+
+    #[test]
+    fn test_rules_follow_the_match_mode() {
+        let rules = ColumnRules::new().rule("zip", r"\d{3}").unwrap();
+        let options = SearchOptions::new().match_mode(MatchMode::FullField);
+        let matches = find_rule_matches_in(SIGNUPS.as_bytes(), &rules, &options).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].found.value, "123");
+    }
+
+    #[test]
+    fn test_all_mode_requires_every_rule() {
+        let rules = rules().mode(RuleMode::All);
+        let matches = find_rule_matches_in(SIGNUPS.as_bytes(), &rules, &SearchOptions::default()).unwrap();
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].found.header.as_deref(), Some("email"));
+        assert_eq!(matches[1].found.value, "28001");
+    }
+
+    #[test]
+    fn test_unknown_rule_column() {
+        let rules = ColumnRules::new().rule("phone", r"^\d+$").unwrap();
+        let result = find_rule_matches_in(SIGNUPS.as_bytes(), &rules, &SearchOptions::default());
+
+        assert!(matches!(result, Err(CoincidenceError::UnknownColumn(_))));
+    }
+}
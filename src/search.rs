@@ -0,0 +1,218 @@
+use crate::columns::ColumnFilter;
+use crate::pattern::Matcher;
+use crate::query::RecordFilter;
+use crate::{validate_csv_extension, CoincidenceError, Match, Pattern, SearchOptions};
+use csv::{Reader, StringRecord};
+use std::fs::File;
+use std::io::Read;
+
+/// A lazy iterator over the matches found in CSV data.
+///
+/// Records are read one at a time as the iterator advances, so memory use stays bounded regardless of the input size
+/// and callers can stop early. After an error is yielded the iterator is exhausted.
+///
+/// This iterator is created by [`matches`] and [`matches_in`].
+pub struct Matches<R> {
+    rdr: Reader<R>,
+    matcher: Matcher,
+    headers: Option<StringRecord>,
+    filter: ColumnFilter,
+    record_filter: RecordFilter,
+    record: StringRecord,
+    record_number: u64,
+    column: usize,
+    done: bool,
+}
+
+impl<R: Read> Matches<R> {
+    pub(crate) fn new(reader: R, matcher: Matcher, options: &SearchOptions) -> Result<Self, CoincidenceError> {
+        let mut rdr = options.reader_builder().from_reader(reader);
+        let headers = options.read_headers(&mut rdr)?;
+        let filter = options.column_filter(headers.as_ref())?;
+        let record_filter = options.record_filter(headers.as_ref())?;
+
+        Ok(Matches {
+            rdr,
+            matcher,
+            headers,
+            filter,
+            record_filter,
+            record: StringRecord::new(),
+            record_number: 0,
+            column: 0,
+            done: false,
+        })
+    }
+
+    /// Returns the header row of the input, when it has one.
+    pub fn headers(&self) -> Option<&StringRecord> {
+        self.headers.as_ref()
+    }
+}
+
+impl<R: Read> Iterator for Matches<R> {
+    type Item = Result<Match, CoincidenceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            while self.column < self.record.len() {
+                let column = self.column;
+                self.column += 1;
+
+                if !self.filter.is_selected(column) {
+                    continue;
+                }
+
+                if let Some(span) = self.matcher.find(&self.record[column]) {
+                    return Some(Ok(Match::new(
+                        self.record_number,
+                        &self.record,
+                        column,
+                        self.headers.as_ref(),
+                        span,
+                    )));
+                }
+            }
+
+            match self.rdr.read_record(&mut self.record) {
+                Ok(true) => {
+                    self.record_number += 1;
+                    self.column = match self.record_filter.is_selected(&self.record) {
+                        true => 0,
+                        false => self.record.len(),
+                    };
+                }
+                Ok(false) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Lazily searches the CSV file for matches of the given regular expression pattern.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice representing the file path to the input CSV file.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV file is read.
+///
+/// # Returns
+///
+/// A `Result` containing a [`Matches`] iterator that yields each [`Match`] as the file is read, or an error if the
+/// search could not be started.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the file is not a valid CSV file, cannot be opened, if its header row is
+/// malformed, if a selected column does not exist, or if the regular expression pattern is invalid. Errors
+/// found while reading later records are yielded by the iterator.
+///
+/// # Example
+///
+/// ```no_run
+/// use csv_coincidence::{matches, SearchOptions};
+///
+/// let first_ten = matches("huge.csv", r"ERROR", &SearchOptions::default())
+///     .unwrap()
+///     .take(10)
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// ```
+pub fn matches<P: Into<Pattern>>(
+    file_path: &str,
+    regex_pattern: P,
+    options: &SearchOptions,
+) -> Result<Matches<File>, CoincidenceError> {
+    validate_csv_extension(file_path)?;
+
+    let matcher = options.matcher(&regex_pattern.into())?;
+    let file = File::open(file_path)?;
+    Matches::new(file, matcher, options)
+}
+
+/// Lazily searches CSV data read from any `std::io::Read` source for matches of the given regular expression
+/// pattern.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the CSV data.
+/// * `regex_pattern` - The pattern to match against the CSV records: a regular expression string or a [`Pattern`].
+/// * `options` - The [`SearchOptions`] describing how the CSV data is read.
+///
+/// # Returns
+///
+/// A `Result` containing a [`Matches`] iterator that yields each [`Match`] as the data is read, or an error if the
+/// search could not be started.
+///
+/// # Errors
+///
+/// Returns a [`CoincidenceError`] if the header row is malformed, if a selected column does not exist, or if the
+/// regular expression pattern is invalid. Errors found while reading later records are yielded by the
+/// iterator.
+///
+/// # Example
+///
+/// ```
+/// use csv_coincidence::{matches_in, SearchOptions};
+///
+/// let data = "name,city\nJhon,Madrid\nMarta,Paris\n";
+/// let mut found = matches_in(data.as_bytes(), r"^[A-Z]", &SearchOptions::default()).unwrap();
+///
+/// assert_eq!(found.next().unwrap().unwrap().value, "Jhon");
+/// assert_eq!(found.next().unwrap().unwrap().value, "Madrid");
+/// ```
+pub fn matches_in<R: Read, P: Into<Pattern>>(
+    reader: R,
+    regex_pattern: P,
+    options: &SearchOptions,
+) -> Result<Matches<R>, CoincidenceError> {
+    let matcher = options.matcher(&regex_pattern.into())?;
+    Matches::new(reader, matcher, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// A reader that fails once the wrapped data is exhausted, to prove the iterator stops before reading it.
+    struct FailAfter<'a>(&'a [u8]);
+
+    impl Read for FailAfter<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("read past the requested matches"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_matches_stops_early() {
+        let data = "name\nJhon\nMarta\n";
+        let found: Vec<Match> = matches_in(FailAfter(data.as_bytes()), r"^[A-Z]", &SearchOptions::default())
+            .unwrap()
+            .take(1)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].value, "Jhon");
+    }
+
+    #[test]
+    fn test_matches_yields_errors_and_ends() {
+        let data = "a,b\nx,1\ny\nz,3\n";
+        let mut found = matches_in(data.as_bytes(), r"^[a-z]$", &SearchOptions::default()).unwrap();
+
+        assert_eq!(found.next().unwrap().unwrap().value, "x");
+        assert!(matches!(found.next(), Some(Err(CoincidenceError::Csv { .. }))));
+        assert!(found.next().is_none());
+    }
+}